@@ -0,0 +1,66 @@
+use std::fmt;
+
+/// Graphviz graph, nodes and edges are written in the order they are added
+#[derive(Debug, Clone)]
+pub struct Dot {
+    pub name: String,
+    pub directed: bool,
+    lines: Vec<String>,
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn attributes(attrs: &[(&str, &str)]) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let attrs: Vec<String> = attrs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, quote(v)))
+        .collect();
+    format!(" [{}]", attrs.join(", "))
+}
+
+impl Dot {
+    pub fn new(name: &str, directed: bool) -> Self {
+        Dot {
+            name: name.to_string(),
+            directed,
+            lines: vec![],
+        }
+    }
+
+    /// Attributes shared by every node, e.g. `("shape", "box")`
+    pub fn default_node(&mut self, attrs: &[(&str, &str)]) {
+        self.lines.push(format!("node{};", attributes(attrs)));
+    }
+
+    pub fn node(&mut self, id: &str, attrs: &[(&str, &str)]) {
+        self.lines
+            .push(format!("{}{};", quote(id), attributes(attrs)));
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, attrs: &[(&str, &str)]) {
+        let arrow = if self.directed { "->" } else { "--" };
+        self.lines.push(format!(
+            "{} {} {}{};",
+            quote(from),
+            arrow,
+            quote(to),
+            attributes(attrs)
+        ));
+    }
+}
+
+impl fmt::Display for Dot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.directed { "digraph" } else { "graph" };
+        writeln!(f, "{} {} {{", kind, quote(&self.name))?;
+        for line in self.lines.iter() {
+            writeln!(f, "    {}", line)?;
+        }
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,86 @@
+/// Directions on a grid where y grows downwards, like the puzzle inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Dir {
+    pub fn delta(&self) -> (i128, i128) {
+        match self {
+            Dir::Up => (0, -1),
+            Dir::Down => (0, 1),
+            Dir::Left => (-1, 0),
+            Dir::Right => (1, 0),
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Closed lattice polygon, the last vertex connects back to the first one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polygon {
+    pub vertices: Vec<(i128, i128)>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<(i128, i128)>) -> Self {
+        Polygon { vertices }
+    }
+
+    /// Walks `(direction, length)` steps from the origin
+    pub fn from_steps(steps: impl IntoIterator<Item = (Dir, u64)>) -> Self {
+        let mut vertices = vec![];
+        let (mut x, mut y) = (0, 0);
+        for (dir, len) in steps {
+            vertices.push((x, y));
+            let (dx, dy) = dir.delta();
+            x += dx * len as i128;
+            y += dy * len as i128;
+        }
+        Polygon { vertices }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = ((i128, i128), (i128, i128))> + '_ {
+        let next = self.vertices.iter().cycle().skip(1);
+        self.vertices.iter().copied().zip(next.copied())
+    }
+
+    /// Shoelace formula, positive when the vertices go counter clockwise
+    /// with y pointing up (so clockwise on a puzzle grid)
+    ///
+    /// https://en.wikipedia.org/wiki/Shoelace_formula
+    pub fn twice_signed_area(&self) -> i128 {
+        self.edges().map(|(a, b)| a.0 * b.1 - b.0 * a.1).sum()
+    }
+
+    pub fn twice_area(&self) -> i128 {
+        self.twice_signed_area().abs()
+    }
+
+    /// Lattice points lying on the edges
+    pub fn boundary_points(&self) -> i128 {
+        self.edges().map(|(a, b)| gcd(b.0 - a.0, b.1 - a.1)).sum()
+    }
+
+    /// Lattice points strictly inside, with Pick's theorem
+    ///
+    /// https://en.wikipedia.org/wiki/Pick%27s_theorem
+    pub fn interior_points(&self) -> i128 {
+        (self.twice_area() - self.boundary_points() + 2) / 2
+    }
+
+    /// Grid cells covered when every lattice point is a cell
+    pub fn enclosed_cells(&self) -> i128 {
+        self.interior_points() + self.boundary_points()
+    }
+}
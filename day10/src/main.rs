@@ -1,167 +1,249 @@
-use std::{collections::VecDeque, str::FromStr, time::Instant};
+use std::{str::FromStr, time::Instant};
 
-use color_eyre::eyre::{Error, Result};
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
+use common::polygon::Polygon;
 
-#[derive(Debug, Clone)]
-pub struct Loop {
-    pub width: usize,
-    pub height: usize,
-    pub start: Pipe,
-    pub pipes: Vec<Pipe>,
-}
+const NORTH: u8 = 1;
+const EAST: u8 = 2;
+const SOUTH: u8 = 4;
+const WEST: u8 = 8;
+const DIRS: [u8; 4] = [NORTH, EAST, SOUTH, WEST];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct GridCoord {
     pub x: isize,
     pub y: isize,
 }
 
-#[derive(Debug, Clone)]
-pub struct Pipe {
-    pub coord: GridCoord,
-    pub neighbours: Vec<GridCoord>,
-    pub val: char,
+impl GridCoord {
+    fn step(&self, dir: u8) -> GridCoord {
+        let (dx, dy) = match dir {
+            NORTH => (0, -1),
+            EAST => (1, 0),
+            SOUTH => (0, 1),
+            WEST => (-1, 0),
+            _ => unreachable!(),
+        };
+        GridCoord {
+            x: self.x + dx,
+            y: self.y + dy,
+        }
+    }
 }
 
-fn connecting_neighs(
-    width: usize,
-    height: usize,
-    input: &str,
-    c: &GridCoord,
-    val: char,
-) -> Vec<GridCoord> {
-    let left = (GridCoord { x: c.x - 1, y: c.y }, "L-FS");
-    let right = (GridCoord { x: c.x + 1, y: c.y }, "J-7S");
-    let up = (GridCoord { x: c.x, y: c.y - 1 }, "7|FS");
-    let down = (GridCoord { x: c.x, y: c.y + 1 }, "J|LS");
-
-    let dirs = match val {
-        '|' => vec![up, down],
-        '-' => vec![left, right],
-        'L' => vec![up, right],
-        'J' => vec![up, left],
-        '7' => vec![left, down],
-        'F' => vec![down, right],
-        // We need points to be counter clockwise, so this order is important
-        'S' => vec![down, left, up, right],
+fn opposite(dir: u8) -> u8 {
+    match dir {
+        NORTH => SOUTH,
+        EAST => WEST,
+        SOUTH => NORTH,
+        WEST => EAST,
         _ => unreachable!(),
-    };
+    }
+}
+
+/// Directions a tile connects to
+fn connections(tile: u8) -> u8 {
+    match tile {
+        b'|' => NORTH | SOUTH,
+        b'-' => EAST | WEST,
+        b'L' => NORTH | EAST,
+        b'J' => NORTH | WEST,
+        b'7' => SOUTH | WEST,
+        b'F' => SOUTH | EAST,
+        _ => 0,
+    }
+}
+
+fn shape(connections: u8) -> Option<u8> {
+    b"|-LJ7F"
+        .iter()
+        .copied()
+        .find(|t| self::connections(*t) == connections)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Loop,
+    Inside,
+    Outside,
+}
+
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub width: usize,
+    pub height: usize,
+    /// `S` is replaced by the pipe it hides
+    pub tiles: Vec<u8>,
+    pub start: GridCoord,
+    /// Loop tiles in walking order, starting with `start`
+    pub pipes: Vec<GridCoord>,
+}
+
+impl Loop {
+    fn idx(&self, c: GridCoord) -> usize {
+        c.y as usize * self.width + c.x as usize
+    }
 
-    let mut neighs = vec![];
-    for dir in dirs {
-        let (new_c, possible_vals) = dir;
-        if new_c.x < 0 || new_c.x >= width as isize || new_c.y < 0 || new_c.y >= height as isize {
-            continue;
+    fn tile(&self, c: GridCoord) -> u8 {
+        if c.x < 0 || c.x >= self.width as isize || c.y < 0 || c.y >= self.height as isize {
+            return b'.';
         }
-        let i = new_c.y as usize * width + new_c.x as usize;
-        let new_val = &input[i..i + 1];
-        if possible_vals.contains(new_val) {
-            neighs.push(new_c);
+        self.tiles[self.idx(c)]
+    }
+
+    fn connects(&self, c: GridCoord, dir: u8) -> bool {
+        connections(self.tile(c)) & dir != 0
+            && connections(self.tile(c.step(dir))) & opposite(dir) != 0
+    }
+
+    /// Pipes the start could hide, from each pair of neighbours pointing at it
+    fn start_shapes(&self) -> Vec<u8> {
+        let dirs: Vec<u8> = DIRS
+            .into_iter()
+            .filter(|d| connections(self.tile(self.start.step(*d))) & opposite(*d) != 0)
+            .collect();
+        let mut shapes = vec![];
+        for (i, a) in dirs.iter().enumerate() {
+            for b in dirs[i + 1..].iter() {
+                shapes.extend(shape(a | b));
+            }
         }
+        shapes
     }
 
-    neighs
-}
+    fn trace(&mut self) -> Result<()> {
+        let mut dir = *DIRS
+            .iter()
+            .find(|d| self.connects(self.start, **d))
+            .wrap_err("start is not connected")?;
+        let mut cur = self.start;
+        loop {
+            self.pipes.push(cur);
+            cur = cur.step(dir);
+            if cur == self.start {
+                return Ok(());
+            }
+            let came_from = opposite(dir);
+            dir = connections(self.tile(cur)) & !came_from;
+            if !self.connects(cur, dir) {
+                return Err(eyre!("loop is broken at {:?}", cur));
+            }
+        }
+    }
 
-impl FromStr for Loop {
-    type Err = Error;
+    fn polygon(&self) -> Polygon {
+        let vertices = self.pipes.iter().map(|p| (p.x as i128, p.y as i128));
+        Polygon::new(vertices.collect())
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let height = s.lines().count();
-        let width = s.lines().next().unwrap().len();
-        let map: String = s.lines().collect();
-        let mut it = map.chars();
-        let mut start = None;
-
-        for y in 0..height {
-            for x in 0..width {
-                let c = it.next().unwrap();
-                if c == 'S' {
-                    let coord = GridCoord {
-                        x: x as isize,
-                        y: y as isize,
-                    };
-                    let neighs = connecting_neighs(width, height, &map, &coord, c);
-                    let p = Pipe {
-                        coord,
-                        neighbours: neighs,
-                        val: c,
-                    };
-                    start = Some(p);
+    /// Scanline classification, a tile is inside when an odd number of
+    /// north-going pipes lie to its left
+    fn labels(&self) -> Vec<Label> {
+        let mut on_loop = vec![false; self.tiles.len()];
+        for p in self.pipes.iter() {
+            on_loop[self.idx(*p)] = true;
+        }
+
+        let mut labels = vec![Label::Outside; self.tiles.len()];
+        for y in 0..self.height {
+            let mut inside = false;
+            for x in 0..self.width {
+                let i = y * self.width + x;
+                if on_loop[i] {
+                    labels[i] = Label::Loop;
+                    inside ^= connections(self.tiles[i]) & NORTH != 0;
+                } else if inside {
+                    labels[i] = Label::Inside;
                 }
             }
         }
+        labels
+    }
 
-        let mut pipes: Vec<Pipe> = vec![];
-        let mut queue = vec![start.clone().unwrap()];
-        let mut visited = vec![];
-        while let Some(pipe) = queue.pop() {
-            for neigh_c in pipe.neighbours.iter() {
-                if pipes.iter().all(|p| p.coord != *neigh_c) && !visited.contains(neigh_c) {
-                    visited.push(neigh_c.clone());
-                    let i = neigh_c.y as usize * width + neigh_c.x as usize;
-                    let val = &map[i..i + 1].chars().next().unwrap();
-                    let neighs = connecting_neighs(width, height, &map, &neigh_c, *val);
-                    let p = Pipe {
-                        coord: neigh_c.clone(),
-                        neighbours: neighs,
-                        val: *val,
-                    };
-                    queue.push(p);
-                }
+    fn render(&self, labels: &[Label]) -> String {
+        let mut s = String::new();
+        for (i, label) in labels.iter().enumerate() {
+            if i > 0 && i % self.width == 0 {
+                s.push('\n');
             }
-            pipes.push(pipe);
+            s.push(match label {
+                Label::Loop if i == self.idx(self.start) => 'S',
+                Label::Loop => self.tiles[i] as char,
+                Label::Inside => 'I',
+                Label::Outside => 'O',
+            });
         }
-        Ok(Loop {
-            width,
-            height,
-            start: start.unwrap(),
-            pipes,
-        })
+        s
     }
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let lp: Loop = input.parse().unwrap();
-
-    // Basic BFS
-    let mut queue = VecDeque::new();
-    queue.push_front((&lp.start, 0));
-    let mut visited = vec![];
-    let mut best_dist = 0;
-    while let Some(next) = queue.pop_front() {
-        let (pipe, cur_dist) = next;
-        if cur_dist > best_dist {
-            best_dist = cur_dist;
-        }
-        for neigh in pipe.neighbours.iter() {
-            if !visited.contains(neigh) {
-                let p = lp.pipes.iter().find(|pp| pp.coord == *neigh).unwrap();
-                queue.push_back((p, cur_dist + 1));
-                visited.push(neigh.clone());
+impl FromStr for Loop {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let height = s.lines().count();
+        let width = s.lines().next().wrap_err("empty maze")?.len();
+        let tiles: Vec<u8> = s.lines().flat_map(|l| l.bytes()).collect();
+        let i = tiles.iter().position(|t| *t == b'S').wrap_err("no start")?;
+        let start = GridCoord {
+            x: (i % width) as isize,
+            y: (i / width) as isize,
+        };
+
+        let mut lp = Loop {
+            width,
+            height,
+            tiles,
+            start,
+            pipes: vec![],
+        };
+        // stray pipes may point at the start too, keep the shape closing the loop
+        let i = lp.idx(start);
+        let mut err = eyre!("start connects to less than 2 pipes");
+        for shape in lp.start_shapes() {
+            lp.tiles[i] = shape;
+            lp.pipes.clear();
+            match lp.trace() {
+                Ok(()) => return Ok(lp),
+                Err(e) => err = e,
             }
         }
+        Err(err)
     }
+}
 
-    Ok(best_dist)
+fn part1(input: &str) -> Result<usize> {
+    let lp: Loop = input.parse()?;
+    Ok(lp.pipes.len() / 2)
 }
 
 fn part2(input: &str) -> Result<usize> {
-    let lp: Loop = input.parse().unwrap();
-
-    // Compute area with the Shoelace formula
-    // https://en.wikipedia.org/wiki/Shoelace_formula
-    let mut sum = 0;
-    for p in lp.pipes.windows(2) {
-        sum += (p[0].coord.y + p[1].coord.y) * (p[0].coord.x - p[1].coord.x)
-    }
-    let area = 0.5 * sum.abs() as f64;
+    let lp: Loop = input.parse()?;
+    Ok(lp.polygon().interior_points() as usize)
+}
 
-    // Find interior points with Pick's theorem
-    // https://en.wikipedia.org/wiki/Pick%27s_theorem
-    let i = area - (lp.pipes.len() as f64 / 2.0) + 1.0;
+fn print_map(input: &str) -> Result<()> {
+    let lp: Loop = input.parse()?;
+    let labels = lp.labels();
+    println!("{}", lp.render(&labels));
 
-    Ok(i as usize)
+    let scanline = labels.iter().filter(|l| **l == Label::Inside).count();
+    let polygon = lp.polygon();
+    let orientation = if polygon.twice_signed_area() > 0 {
+        "clockwise"
+    } else {
+        "counter clockwise"
+    };
+    println!("[*] loop walked {}", orientation);
+    println!(
+        "[*] inside: {} (pick), {} (scanline)",
+        polygon.interior_points(),
+        scanline
+    );
+    if scanline as i128 != polygon.interior_points() {
+        return Err(eyre!("inside counts disagree"));
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -169,6 +251,10 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    if std::env::args().nth(1).as_deref() == Some("--map") {
+        return print_map(input);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
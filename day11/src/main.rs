@@ -1,7 +1,6 @@
 use std::{str::FromStr, time::Instant};
 
-use color_eyre::eyre::{Error, Result};
-use itertools::Itertools;
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
 
 #[derive(Debug)]
 pub struct Universe {
@@ -10,10 +9,10 @@ pub struct Universe {
     pub galaxies: Vec<GridCoord>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct GridCoord {
-    pub x: isize,
-    pub y: isize,
+    pub x: u128,
+    pub y: u128,
 }
 
 impl FromStr for Universe {
@@ -21,14 +20,14 @@ impl FromStr for Universe {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let height = s.lines().count();
-        let width = s.lines().next().unwrap().len();
+        let width = s.lines().next().wrap_err("empty universe")?.len();
         let mut galaxies = vec![];
         for (y, l) in s.lines().enumerate() {
             for (x, c) in l.chars().enumerate() {
                 if c == '#' {
                     galaxies.push(GridCoord {
-                        x: x as isize,
-                        y: y as isize,
+                        x: x as u128,
+                        y: y as u128,
                     });
                 }
             }
@@ -41,66 +40,130 @@ impl FromStr for Universe {
     }
 }
 
-fn add_expansion(input: &str, universe: &mut Universe, dist: usize) {
-    let dist = 1 + dist.checked_sub(2).unwrap_or(0);
-
-    let mut offset = 0;
-    for (y, l) in input.lines().enumerate() {
-        if !l.contains("#") {
-            for galaxy in universe
-                .galaxies
-                .iter_mut()
-                .filter(|g| g.y > y as isize + offset)
-            {
-                galaxy.y += dist as isize;
-            }
-            offset += dist as isize;
-            universe.height += dist;
-        }
+/// Position of each line once every empty line is `factor` lines wide
+fn expanded_lines(len: usize, coords: impl Iterator<Item = u128>, factor: u128) -> Vec<u128> {
+    let mut occupied = vec![false; len];
+    for c in coords {
+        occupied[c as usize] = true;
+    }
+    let mut res = Vec::with_capacity(len);
+    let mut pos = 0;
+    for o in occupied {
+        res.push(pos);
+        pos += if o { 1 } else { factor };
     }
+    res
+}
 
-    let mut offset = 0;
-    for x in 0..universe.width {
-        if input.lines().all(|l| l.chars().skip(x).next() == Some('.')) {
-            for galaxy in universe
-                .galaxies
-                .iter_mut()
-                .filter(|g| g.x > x as isize + offset)
-            {
-                galaxy.x += dist as isize;
-            }
-            universe.width += dist;
-            offset += dist as isize;
-        }
+/// Sum of `|a - b|` over all pairs
+fn pair_sum(mut values: Vec<u128>) -> u128 {
+    values.sort_unstable();
+    let mut prefix = 0;
+    let mut sum = 0;
+    for (i, v) in values.into_iter().enumerate() {
+        sum += v * i as u128 - prefix;
+        prefix += v;
     }
+    sum
 }
 
 impl GridCoord {
-    pub fn manhattan_dist(&self, other: &Self) -> isize {
-        (self.x - other.x).abs() + (self.y - other.y).abs()
+    pub fn manhattan_dist(&self, other: &Self) -> u128 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
     }
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let mut universe: Universe = input.parse()?;
-    add_expansion(input, &mut universe, 1);
-    let mut sum = 0;
-    for (g1, g2) in universe.galaxies.iter().tuple_combinations() {
-        let dist = g1.manhattan_dist(g2);
-        sum += dist;
+impl Universe {
+    /// Galaxies once each empty row and column is `factor` times as large
+    pub fn expand(&self, factor: u128) -> Vec<GridCoord> {
+        let xs = expanded_lines(self.width, self.galaxies.iter().map(|g| g.x), factor);
+        let ys = expanded_lines(self.height, self.galaxies.iter().map(|g| g.y), factor);
+        self.galaxies
+            .iter()
+            .map(|g| GridCoord {
+                x: xs[g.x as usize],
+                y: ys[g.y as usize],
+            })
+            .collect()
     }
-    Ok(sum as usize)
+
+    /// Sum of the distances between every pair of galaxies, one axis at a time
+    pub fn distances(&self, factor: u128) -> u128 {
+        let galaxies = self.expand(factor);
+        pair_sum(galaxies.iter().map(|g| g.x).collect())
+            + pair_sum(galaxies.iter().map(|g| g.y).collect())
+    }
+
+    /// Closest other galaxy of each galaxy along with its distance
+    pub fn nearest(&self, factor: u128) -> Vec<Option<(usize, u128)>> {
+        let galaxies = self.expand(factor);
+        let mut order: Vec<usize> = (0..galaxies.len()).collect();
+        order.sort_unstable_by_key(|i| galaxies[*i].x);
+
+        let mut res = vec![None; galaxies.len()];
+        for (oi, &gi) in order.iter().enumerate() {
+            let g = galaxies[gi];
+            let mut best: Option<(usize, u128)> = None;
+            // walk away from the galaxy along x until nothing can be closer
+            let mut visit = |other: usize| {
+                let o = galaxies[other];
+                if best.is_some_and(|(_, d)| o.x.abs_diff(g.x) > d) {
+                    return false;
+                }
+                let d = g.manhattan_dist(&o);
+                if best.is_none_or(|(_, bd)| d < bd) {
+                    best = Some((other, d));
+                }
+                true
+            };
+            for other in order[..oi].iter().rev() {
+                if !visit(*other) {
+                    break;
+                }
+            }
+            for other in order[oi + 1..].iter() {
+                if !visit(*other) {
+                    break;
+                }
+            }
+            res[gi] = best;
+        }
+        res
+    }
+}
+
+fn part1(input: &str) -> Result<usize> {
+    let universe: Universe = input.parse()?;
+    Ok(universe.distances(2).try_into()?)
 }
 
 fn part2(input: &str) -> Result<usize> {
-    let mut universe: Universe = input.parse()?;
-    add_expansion(input, &mut universe, 1_000_000);
-    let mut sum = 0;
-    for (g1, g2) in universe.galaxies.iter().tuple_combinations() {
-        let dist = g1.manhattan_dist(g2);
-        sum += dist;
+    let universe: Universe = input.parse()?;
+    Ok(universe.distances(1_000_000).try_into()?)
+}
+
+fn query(input: &str, args: &[String]) -> Result<()> {
+    let universe: Universe = input.parse()?;
+    match args {
+        [cmd, factor] if cmd == "--factor" => {
+            println!("[*] distances: {}", universe.distances(factor.parse()?));
+        }
+        [cmd, rest @ ..] if cmd == "--nearest" && rest.len() <= 1 => {
+            let factor = rest.first().map_or(Ok(2), |f| f.parse())?;
+            for (gi, nearest) in universe.nearest(factor).into_iter().enumerate() {
+                let g = universe.galaxies[gi];
+                match nearest {
+                    Some((other, d)) => {
+                        let o = universe.galaxies[other];
+                        println!("({},{}) -> ({},{}): {}", g.x, g.y, o.x, o.y, d);
+                    }
+                    None => println!("({},{}) is alone", g.x, g.y),
+                }
+            }
+        }
+        _ => return Err(eyre!("usage: --factor <f> | --nearest [factor]")),
     }
-    Ok(sum as usize)
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -108,6 +171,11 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return query(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
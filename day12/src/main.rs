@@ -1,86 +1,76 @@
-use std::{collections::HashMap, time::Instant};
+mod nonogram;
+mod springs;
 
-use color_eyre::eyre::Result;
+use std::{fs, time::Instant};
 
-fn dp(
-    c_idx: usize,
-    g_idx: usize,
-    values: &[u8],
-    groups: &[usize],
-    cache: &mut HashMap<(usize, usize), usize>,
-) -> usize {
-    // memoization
-    if let Some(&res) = cache.get(&(c_idx, g_idx)) {
-        return res;
-    }
-    // we reached the end
-    if c_idx >= values.len() {
-        // check if it's a valid arrangement
-        let r = (g_idx == groups.len()) as usize;
-        cache.insert((c_idx, g_idx), r);
-        return r;
-    }
-
-    let mut res = 0;
+use color_eyre::eyre::{eyre, ContextCompat, Result};
+use nonogram::{Nonogram, Solution};
+use springs::Row;
 
-    // if it's a dot, we continue
-    // if it's unknown, we try the dot possiblity
-    if values[c_idx] == b'.' || values[c_idx] == b'?' {
-        res += dp(c_idx + 1, g_idx, values, groups, cache);
-    }
+fn parse(input: &str) -> Result<Vec<Row>> {
+    input.lines().map(|l| l.parse()).collect()
+}
 
-    // if it's a pound, we try to match the current group
-    // if it's unknown, we try the pound possiblity
-    if let Some(&g) = groups.get(g_idx) {
-        let g_end = c_idx + g;
-        if (g_end < values.len() && !values[c_idx..g_end].contains(&b'.') && values[g_end] != b'#')
-            || (g_end == values.len() && !values[c_idx..g_end].contains(&b'.'))
-        {
-            res += dp(g_end + 1, g_idx + 1, values, groups, cache);
-        }
+fn part1(input: &str) -> Result<u128> {
+    let mut total = 0;
+    for row in parse(input)? {
+        total += row.count()?;
     }
-
-    cache.insert((c_idx, g_idx), res);
-    res
+    Ok(total)
 }
 
-fn part1(input: &str) -> Result<usize> {
+fn part2(input: &str) -> Result<u128> {
     let mut total = 0;
-    for line in input.lines() {
-        let (values, groups) = line.split_once(" ").unwrap();
-        let groups = groups
-            .split(",")
-            .map(|v| v.parse::<usize>().unwrap())
-            .collect::<Vec<_>>();
-
-        let mut cache: HashMap<(usize, usize), usize> = HashMap::new();
-        let res = dp(0, 0, values.as_bytes(), &groups, &mut cache);
-        total += res;
+    for row in parse(input)? {
+        total += row.unfold(5, b'?').count()?;
     }
     Ok(total)
 }
 
-fn part2(input: &str) -> Result<usize> {
-    let mut total = 0;
-    for line in input.lines() {
-        let (values, groups) = line.split_once(" ").unwrap();
-        let groups = groups
-            .split(",")
-            .map(|v| v.parse::<usize>().unwrap())
-            .collect::<Vec<_>>();
-        let mut new_values = values.to_string();
-        let mut new_groups = groups.clone();
-        for _ in 0..4 {
-            new_values.push('?');
-            new_values.push_str(values);
-            new_groups.extend_from_slice(&groups);
-        }
+fn solve_nonogram(path: &str) -> Result<()> {
+    let nonogram: Nonogram = fs::read_to_string(path)?.parse()?;
+    match nonogram.solve()? {
+        Solution::Unique(grid) => println!("[*] unique solution\n{}", nonogram::render(&grid)),
+        Solution::Multiple(a, b) => println!(
+            "[*] multiple solutions\n{}\n\n{}",
+            nonogram::render(&a),
+            nonogram::render(&b)
+        ),
+        Solution::None => println!("[*] no solution"),
+    }
+    Ok(())
+}
 
-        let mut cache: HashMap<(usize, usize), usize> = HashMap::new();
-        let res = dp(0, 0, new_values.as_bytes(), &new_groups, &mut cache);
-        total += res;
+fn explore(input: &str, args: &[String]) -> Result<()> {
+    if let [cmd, path] = args {
+        if cmd == "--nonogram" {
+            return solve_nonogram(path);
+        }
     }
-    Ok(total)
+    let rows = parse(input)?;
+    let [cmd, line, n] = args else {
+        return Err(eyre!(
+            "usage: --list|--sample <line> <n> or --nonogram <file>"
+        ));
+    };
+    let row = rows.get(line.parse::<usize>()?).wrap_err("no such line")?;
+    let n: usize = n.parse()?;
+    match cmd.as_str() {
+        "--list" => {
+            for a in row.arrangements(n)? {
+                println!("{}", a);
+            }
+        }
+        "--sample" => {
+            let mut rng = rand::thread_rng();
+            for _ in 0..n {
+                let a = row.sample(&mut rng)?.wrap_err("no arrangement")?;
+                println!("{}", a);
+            }
+        }
+        _ => return Err(eyre!("unknown argument {}", cmd)),
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -88,6 +78,12 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    // e.g. `cargo run -- --sample 3 10`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return explore(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
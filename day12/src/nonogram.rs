@@ -0,0 +1,156 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
+
+use crate::springs::Row;
+
+/// Cells are `#`, `.` or `?` when still unknown
+pub type Grid = Vec<Vec<u8>>;
+
+#[derive(Debug)]
+pub enum Solution {
+    Unique(Grid),
+    /// Two of the possible solutions
+    Multiple(Grid, Grid),
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct Nonogram {
+    pub rows: Vec<Vec<usize>>,
+    pub cols: Vec<Vec<usize>>,
+}
+
+fn parse_clues(s: &str) -> Result<Vec<Vec<usize>>> {
+    s.lines()
+        .map(|l| {
+            let clue = l
+                .split(',')
+                .map(|v| v.trim().parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()?;
+            // a single 0 is an empty line
+            Ok(clue.into_iter().filter(|v| *v != 0).collect())
+        })
+        .collect()
+}
+
+/// Row clues, a blank line, then column clues, one comma separated clue per line
+impl FromStr for Nonogram {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rows, cols) = s.split_once("\n\n").wrap_err("no column clues")?;
+        Ok(Nonogram {
+            rows: parse_clues(rows)?,
+            cols: parse_clues(cols)?,
+        })
+    }
+}
+
+pub fn render(grid: &Grid) -> String {
+    grid.iter()
+        .map(|l| String::from_utf8_lossy(l).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Fixes every cell that has the same value in all arrangements of the line,
+/// returns false if the line has no arrangement left
+fn solve_line(cells: &mut [u8], groups: &[usize]) -> Result<bool> {
+    let mut row = Row {
+        values: cells.to_vec(),
+        groups: groups.to_vec(),
+    };
+    if row.count()? == 0 {
+        return Ok(false);
+    }
+    for (i, cell) in cells.iter_mut().enumerate() {
+        if *cell != b'?' {
+            continue;
+        }
+        row.values[i] = b'#';
+        if row.count()? == 0 {
+            *cell = b'.';
+        } else {
+            row.values[i] = b'.';
+            if row.count()? == 0 {
+                *cell = b'#';
+            }
+        }
+        row.values[i] = *cell;
+    }
+    Ok(true)
+}
+
+impl Nonogram {
+    /// Line solving until nothing changes, returns false on a contradiction
+    fn propagate(&self, grid: &mut Grid) -> Result<bool> {
+        let width = self.cols.len();
+        let mut dirty_rows = vec![true; self.rows.len()];
+        let mut dirty_cols = vec![true; width];
+        while dirty_rows.contains(&true) || dirty_cols.contains(&true) {
+            for y in 0..self.rows.len() {
+                if !std::mem::take(&mut dirty_rows[y]) {
+                    continue;
+                }
+                let before = grid[y].clone();
+                if !solve_line(&mut grid[y], &self.rows[y])? {
+                    return Ok(false);
+                }
+                for x in 0..width {
+                    dirty_cols[x] |= before[x] != grid[y][x];
+                }
+            }
+            for x in 0..width {
+                if !std::mem::take(&mut dirty_cols[x]) {
+                    continue;
+                }
+                let mut col: Vec<u8> = grid.iter().map(|l| l[x]).collect();
+                let before = col.clone();
+                if !solve_line(&mut col, &self.cols[x])? {
+                    return Ok(false);
+                }
+                for y in 0..self.rows.len() {
+                    dirty_rows[y] |= before[y] != col[y];
+                    grid[y][x] = col[y];
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn search(&self, mut grid: Grid, found: &mut Vec<Grid>) -> Result<()> {
+        if found.len() >= 2 || !self.propagate(&mut grid)? {
+            return Ok(());
+        }
+        let unknown = grid
+            .iter()
+            .enumerate()
+            .find_map(|(y, l)| l.iter().position(|v| *v == b'?').map(|x| (x, y)));
+        let Some((x, y)) = unknown else {
+            found.push(grid);
+            return Ok(());
+        };
+        for v in [b'#', b'.'] {
+            let mut guess = grid.clone();
+            guess[y][x] = v;
+            self.search(guess, found)?;
+        }
+        Ok(())
+    }
+
+    pub fn solve(&self) -> Result<Solution> {
+        if self.rows.is_empty() || self.cols.is_empty() {
+            return Err(eyre!("empty nonogram"));
+        }
+        let grid = vec![vec![b'?'; self.cols.len()]; self.rows.len()];
+        let mut found = vec![];
+        self.search(grid, &mut found)?;
+        let mut found = found.into_iter();
+        Ok(match (found.next(), found.next()) {
+            (Some(a), Some(b)) => Solution::Multiple(a, b),
+            (Some(a), None) => Solution::Unique(a),
+            _ => Solution::None,
+        })
+    }
+}
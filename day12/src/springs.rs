@@ -0,0 +1,180 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{ContextCompat, Error, Result};
+use rand::Rng;
+
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub values: Vec<u8>,
+    pub groups: Vec<usize>,
+}
+
+impl FromStr for Row {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (values, groups) = s.split_once(' ').wrap_err("invalid row")?;
+        let groups = groups
+            .split(',')
+            .map(|v| v.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Row {
+            values: values.as_bytes().to_vec(),
+            groups,
+        })
+    }
+}
+
+/// `counts[c * width + g]` is the number of arrangements of `values[c..]` using `groups[g..]`
+struct Table {
+    width: usize,
+    counts: Vec<u128>,
+}
+
+impl Table {
+    fn get(&self, c: usize, g: usize) -> u128 {
+        self.counts[c * self.width + g]
+    }
+}
+
+impl Row {
+    pub fn unfold(&self, factor: usize, sep: u8) -> Row {
+        let mut values = vec![];
+        for i in 0..factor {
+            if i > 0 {
+                values.push(sep);
+            }
+            values.extend_from_slice(&self.values);
+        }
+        Row {
+            values,
+            groups: self.groups.repeat(factor),
+        }
+    }
+
+    /// Where group `g` would end if it started at `c`, when it fits there
+    fn group_end(&self, dots: &[usize], c: usize, g: usize) -> Option<usize> {
+        let end = c + self.groups.get(g)?;
+        if end > self.values.len() || dots[end] != dots[c] {
+            return None;
+        }
+        if end < self.values.len() && self.values[end] == b'#' {
+            return None;
+        }
+        Some(end)
+    }
+
+    fn table(&self) -> Result<(Table, Vec<usize>)> {
+        let n = self.values.len();
+        let m = self.groups.len();
+
+        // dots[i] is the number of '.' before i
+        let mut dots = vec![0; n + 1];
+        for (i, v) in self.values.iter().enumerate() {
+            dots[i + 1] = dots[i] + (*v == b'.') as usize;
+        }
+
+        let width = m + 1;
+        let mut counts = vec![0u128; (n + 1) * width];
+        counts[n * width + m] = 1;
+        for c in (0..n).rev() {
+            for g in 0..=m {
+                let mut res: u128 = 0;
+                if self.values[c] != b'#' {
+                    res = counts[(c + 1) * width + g];
+                }
+                if let Some(end) = self.group_end(&dots, c, g) {
+                    let next = (end + 1).min(n);
+                    res = res
+                        .checked_add(counts[next * width + g + 1])
+                        .wrap_err("arrangement count overflow")?;
+                }
+                counts[c * width + g] = res;
+            }
+        }
+        Ok((Table { width, counts }, dots))
+    }
+
+    pub fn count(&self) -> Result<u128> {
+        let (table, _) = self.table()?;
+        Ok(table.get(0, 0))
+    }
+
+    /// Lists up to `max` valid arrangements, as `#`/`.` rows
+    pub fn arrangements(&self, max: usize) -> Result<Vec<String>> {
+        let (table, dots) = self.table()?;
+        let mut res = vec![];
+        let mut buf = vec![];
+        self.enumerate(&table, &dots, 0, 0, &mut buf, &mut res, max);
+        Ok(res)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn enumerate(
+        &self,
+        table: &Table,
+        dots: &[usize],
+        c: usize,
+        g: usize,
+        buf: &mut Vec<u8>,
+        res: &mut Vec<String>,
+        max: usize,
+    ) {
+        let n = self.values.len();
+        if res.len() >= max || table.get(c, g) == 0 {
+            return;
+        }
+        if c == n {
+            res.push(String::from_utf8(buf.clone()).unwrap());
+            return;
+        }
+
+        if self.values[c] != b'#' {
+            buf.push(b'.');
+            self.enumerate(table, dots, c + 1, g, buf, res, max);
+            buf.pop();
+        }
+        if let Some(end) = self.group_end(dots, c, g) {
+            let len = buf.len();
+            buf.resize(len + end - c, b'#');
+            if end < n {
+                buf.push(b'.');
+            }
+            self.enumerate(table, dots, buf.len(), g + 1, buf, res, max);
+            buf.truncate(len);
+        }
+    }
+
+    /// Picks one of the valid arrangements, each with the same probability
+    pub fn sample(&self, rng: &mut impl Rng) -> Result<Option<String>> {
+        let (table, dots) = self.table()?;
+        let n = self.values.len();
+        if table.get(0, 0) == 0 {
+            return Ok(None);
+        }
+
+        let mut buf = vec![];
+        let (mut c, mut g) = (0, 0);
+        while c < n {
+            let dot = if self.values[c] != b'#' {
+                table.get(c + 1, g)
+            } else {
+                0
+            };
+            let r = rng.gen_range(0..table.get(c, g));
+            if r < dot {
+                buf.push(b'.');
+                c += 1;
+                continue;
+            }
+            let end = self.group_end(&dots, c, g).unwrap();
+            buf.resize(buf.len() + end - c, b'#');
+            if end < n {
+                buf.push(b'.');
+            }
+            c = buf.len();
+            g += 1;
+        }
+        Ok(Some(String::from_utf8(buf).unwrap()))
+    }
+}
@@ -1,110 +1,143 @@
-use std::{fmt::Display, time::Instant};
+use std::{str::FromStr, time::Instant};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
 
-#[derive(Debug, PartialEq, Eq)]
+/// Mirror position, as the number of columns left of it or rows above it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Reflection {
     Vertical(usize),
     Horizontal(usize),
 }
 
-fn reflection<T: AsRef<str> + Display>(array: &[T], old_val: Option<usize>) -> Option<usize> {
-    'col: for l1 in 0..array.len() - 1 {
-        if array[l1].as_ref() == array[l1 + 1].as_ref() {
-            for (i1, i2) in (0..=l1).rev().zip(l1 + 1..array.len()) {
-                if array[i1].as_ref() != array[i2].as_ref() {
-                    continue 'col;
-                }
-            }
-            if let Some(old_val) = old_val {
-                if l1 == old_val {
-                    continue;
-                }
-            }
-            return Some(l1);
+impl Reflection {
+    fn summary(&self) -> usize {
+        match self {
+            Reflection::Vertical(n) => *n,
+            Reflection::Horizontal(n) => 100 * n,
         }
     }
-    None
 }
 
-fn part1(input: &str) -> Result<(usize, Vec<Reflection>)> {
-    let mut hor = 0;
-    let mut ver = 0;
-    let mut res = Vec::new();
-
-    for pattern in input.split("\n\n") {
-        let lines: Vec<&str> = pattern.lines().collect();
-        let mut cols = Vec::new();
-        for i in 0..lines[0].len() {
-            let mut col = String::new();
-            for line in &lines {
-                col.push(line.as_bytes()[i] as char);
+/// Mirror along with the `(x, y)` cells to fix for it to reflect perfectly,
+/// taken on the left or top side
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mirror {
+    reflection: Reflection,
+    smudges: Vec<(usize, usize)>,
+}
+
+/// Rocks as bitmasks, bit `x` of `rows[y]` and bit `y` of `cols[x]`
+#[derive(Debug, Clone)]
+struct Pattern {
+    rows: Vec<u64>,
+    cols: Vec<u64>,
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let height = s.lines().count();
+        let width = s.lines().next().wrap_err("empty pattern")?.len();
+        if width > 64 || height > 64 {
+            return Err(eyre!("{}x{} pattern does not fit in u64", width, height));
+        }
+        let mut rows = vec![0; height];
+        let mut cols = vec![0; width];
+        for (y, l) in s.lines().enumerate() {
+            for (x, c) in l.bytes().enumerate() {
+                match c {
+                    b'#' => {
+                        rows[y] |= 1 << x;
+                        cols[x] |= 1 << y;
+                    }
+                    b'.' => {}
+                    _ => return Err(eyre!("invalid cell {:?}", c as char)),
+                }
             }
-            cols.push(col);
         }
+        Ok(Pattern { rows, cols })
+    }
+}
 
-        if let Some(v) = reflection(&cols, None) {
-            ver += v + 1;
-            res.push(Reflection::Vertical(v));
+/// Mirrors between lines whose halves differ in exactly `k` cells, as the
+/// number of lines before the mirror and `(line, cell)` of each difference
+fn mirrors(lines: &[u64], k: u32) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let mut res = vec![];
+    for m in 1..lines.len() {
+        let pairs = (0..m).rev().zip(m..lines.len());
+        let diff: u32 = pairs
+            .clone()
+            .map(|(a, b)| (lines[a] ^ lines[b]).count_ones())
+            .sum();
+        if diff != k {
+            continue;
         }
-        if let Some(h) = reflection(&lines, None) {
-            hor += h + 1;
-            res.push(Reflection::Horizontal(h));
+        let mut cells = vec![];
+        for (a, b) in pairs {
+            let mut bits = lines[a] ^ lines[b];
+            while bits != 0 {
+                cells.push((a, bits.trailing_zeros() as usize));
+                bits &= bits - 1;
+            }
         }
+        res.push((m, cells));
     }
-    Ok((hor * 100 + ver, res))
+    res
 }
 
-fn part2(input: &str, old_ref: &[Reflection]) -> Result<usize> {
-    let mut hor = 0;
-    let mut ver = 0;
+impl Pattern {
+    /// Every mirror needing exactly `k` smudges fixed
+    fn reflections(&self, k: u32) -> Vec<Mirror> {
+        let vertical = mirrors(&self.cols, k).into_iter().map(|(m, cells)| Mirror {
+            reflection: Reflection::Vertical(m),
+            smudges: cells,
+        });
+        let horizontal = mirrors(&self.rows, k).into_iter().map(|(m, cells)| Mirror {
+            reflection: Reflection::Horizontal(m),
+            smudges: cells.into_iter().map(|(y, x)| (x, y)).collect(),
+        });
+        vertical.chain(horizontal).collect()
+    }
+}
 
-    for (pi, pattern) in input.split("\n\n").enumerate() {
-        for fix in 0..pattern.len() {
-            if pattern[fix..].starts_with("\n") {
-                continue;
-            }
+fn parse(input: &str) -> Result<Vec<Pattern>> {
+    input.split("\n\n").map(|p| p.parse()).collect()
+}
 
-            let mut pattern = pattern.as_bytes().to_vec();
-            match pattern[fix] {
-                b'.' => pattern[fix] = b'#',
-                b'#' => pattern[fix] = b'.',
-                _ => unreachable!(),
-            }
+fn summarize(input: &str, k: u32) -> Result<usize> {
+    let mut total = 0;
+    for pattern in parse(input)? {
+        total += pattern
+            .reflections(k)
+            .iter()
+            .map(|m| m.reflection.summary())
+            .sum::<usize>();
+    }
+    Ok(total)
+}
 
-            let pattern = unsafe { std::str::from_utf8_unchecked(&pattern) };
-            let lines: Vec<&str> = pattern.lines().collect();
-            let mut cols = Vec::new();
-            for i in 0..lines[0].len() {
-                let mut col = String::new();
-                for line in &lines {
-                    col.push(line.as_bytes()[i] as char);
-                }
-                cols.push(col);
-            }
+fn part1(input: &str) -> Result<usize> {
+    summarize(input, 0)
+}
 
-            let mut v1 = None;
-            let mut v2 = None;
+fn part2(input: &str) -> Result<usize> {
+    summarize(input, 1)
+}
 
-            match old_ref[pi] {
-                Reflection::Vertical(v) => {
-                    v1 = Some(v);
-                }
-                Reflection::Horizontal(h) => {
-                    v2 = Some(h);
-                }
-            };
-            if let Some(v) = reflection(&cols, v1) {
-                ver += v + 1;
-                break;
-            }
-            if let Some(h) = reflection(&lines, v2) {
-                hor += h + 1;
-                break;
-            }
+/// Mirrors of each pattern with exactly `k` smudges
+fn print_smudges(input: &str, k: u32) -> Result<()> {
+    for (pi, pattern) in parse(input)?.iter().enumerate() {
+        for m in pattern.reflections(k) {
+            let cells: Vec<String> = m
+                .smudges
+                .iter()
+                .map(|(x, y)| format!("({},{})", x, y))
+                .collect();
+            println!("[*] pattern {}: {:?} {}", pi, m.reflection, cells.join(" "));
         }
     }
-    Ok(hor * 100 + ver)
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -112,13 +145,24 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    // e.g. `cargo run -- --smudges 1`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match &args[..] {
+        [] => {}
+        [cmd, rest @ ..] if cmd == "--smudges" && rest.len() <= 1 => {
+            let k = rest.first().map_or(Ok(1), |k| k.parse())?;
+            return print_smudges(input, k);
+        }
+        _ => return Err(eyre!("usage: --smudges [k]")),
+    }
+
     let instant = Instant::now();
-    let (res, old) = part1(input)?;
+    let res = part1(input)?;
     let time = Instant::now() - instant;
     println!("[*] part 1: {} ({:?})", res, time);
 
     let instant = Instant::now();
-    let res = part2(input, &old)?;
+    let res = part2(input)?;
     let time = Instant::now() - instant;
     println!("[*] part 2: {} ({:?})", res, time);
 
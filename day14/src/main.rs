@@ -1,103 +1,292 @@
-use std::time::Instant;
+use std::{collections::HashMap, str::FromStr, time::Instant};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
 
-fn tilt_dir(width: usize, height: usize, tilt: &mut [u8], dir_i: usize) {
-    const DIRECTIONS: [(isize, isize); 4] = [(0, -1), (-1, 0), (0, 1), (1, 0)];
-    let direction = DIRECTIONS[dir_i];
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    North,
+    West,
+    South,
+    East,
+}
 
-    let it: Box<dyn Iterator<Item = usize>> = if dir_i == 0 || dir_i == 1 {
-        Box::new(0..tilt.len())
-    } else {
-        Box::new((0..tilt.len()).rev())
-    };
+impl TryFrom<char> for Dir {
+    type Error = Error;
 
-    for ci in it {
-        let c = tilt[ci];
-        if c == b'O' {
-            let mut row_i = (ci % width) as isize;
-            let mut col_i = (ci / width) as isize;
-            while (col_i + direction.1) >= 0
-                && (col_i + direction.1) < height as isize
-                && (row_i + direction.0) >= 0
-                && (row_i + direction.0) < width as isize
-                && tilt[(col_i + direction.1) as usize * width + (row_i + direction.0) as usize]
-                    == b'.'
-            {
-                tilt[col_i as usize * width + row_i as usize] = b'.';
-                col_i += direction.1;
-                row_i += direction.0;
-                tilt[col_i as usize * width + row_i as usize] = b'O';
-            }
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            'N' => Ok(Dir::North),
+            'W' => Ok(Dir::West),
+            'S' => Ok(Dir::South),
+            'E' => Ok(Dir::East),
+            _ => Err(eyre!("invalid direction {:?}", c)),
         }
     }
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let mut tilt = input.as_bytes().to_vec();
-    let height = input.lines().count();
-    let width = input.lines().next().unwrap().len() + 1;
-
-    tilt_dir(width, height, &mut tilt, 0);
-    let mut total = 0;
-    for (li, l) in tilt.split(|&c| c == b'\n').enumerate() {
-        total += l.iter().filter(|&c| *c == b'O').count() * (height - li);
+fn parse_dirs(s: &str) -> Result<Vec<Dir>> {
+    s.chars().map(Dir::try_from).collect()
+}
+
+/// Load after some repetitions of a tilt sequence, the states repeat every
+/// `period` repetitions after the first `prefix` ones
+#[derive(Debug, Clone, Copy)]
+struct Spin {
+    load: usize,
+    prefix: usize,
+    period: usize,
+}
+
+/// Stretch of a row or column between cube rocks, `mask` covers `start..end`
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    mask: u128,
+    start: usize,
+    end: usize,
+}
+
+impl Segment {
+    /// Segment holding free bit `j` of a line, `walls` being its cube rocks
+    /// and every bit past its end
+    fn around(walls: u128, j: usize) -> Self {
+        let below = walls & !(u128::MAX << j);
+        let above = walls & (u128::MAX << j);
+        let start = 128 - below.leading_zeros() as usize;
+        let end = above.trailing_zeros() as usize;
+        let mask = (u128::MAX << start) & (u128::MAX >> (128 - end));
+        Segment { mask, start, end }
     }
-    Ok(total)
 }
 
-fn part2(input: &str) -> Result<usize> {
-    let tilt = input.as_bytes().to_vec();
-    let height = input.lines().count();
-    let width = input.lines().next().unwrap().len() + 1;
+/// Last lines a tilt was given and gave back, the latter also transposed.
+/// Lines coming back unchanged are neither refilled nor transposed again.
+#[derive(Debug, Clone)]
+struct TiltCache {
+    input: Vec<u128>,
+    output: Vec<u128>,
+    transposed: Vec<u128>,
+}
 
-    let cycle = |t: &mut Vec<u8>| {
-        for dir_i in 0..4 {
-            tilt_dir(width, height, t, dir_i);
+impl TiltCache {
+    /// Empty lines tilt to empty lines, so an empty cache is already valid
+    fn new(len: usize, other_len: usize) -> Self {
+        TiltCache {
+            input: vec![0; len],
+            output: vec![0; len],
+            transposed: vec![0; other_len],
         }
-    };
-
-    let mut tortoise = tilt.clone();
-    let mut hare = tilt.clone();
+    }
+}
 
-    // find a cycle
-    loop {
-        cycle(&mut tortoise);
-        cycle(&mut hare);
-        cycle(&mut hare);
-        if tortoise == hare {
-            break;
+/// Swaps bit `j` of line `i` with bit `i` of line `j`, by swapping the off
+/// diagonal blocks of halving sizes down to single bits
+fn transpose(lines: &mut [u128; 128]) {
+    let mut s = 64;
+    while s > 0 {
+        let mask = u128::MAX / ((1 << s) + 1);
+        for k in (0..128).filter(|k| k & s == 0) {
+            let t = ((lines[k] >> s) ^ lines[k + s]) & mask;
+            lines[k] ^= t << s;
+            lines[k + s] ^= t;
         }
+        s /= 2;
     }
+}
+
+#[derive(Debug, Clone)]
+struct Platform {
+    /// Cube rocks of each row and column, along with the bits past its end
+    row_walls: Vec<u128>,
+    col_walls: Vec<u128>,
+    /// Round rocks, bit `x` of `rows[y]` and bit `y` of `cols[x]`. A tilt
+    /// works on lines along its direction and keeps the other copy in sync.
+    rows: Vec<u128>,
+    cols: Vec<u128>,
+    /// One per direction, in `Dir` order
+    caches: Vec<TiltCache>,
+}
+
+impl FromStr for Platform {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let height = s.lines().count();
+        let width = s.lines().next().wrap_err("empty platform")?.len();
+        if width == 0 {
+            return Err(eyre!("empty platform"));
+        }
+        if width > 128 || height > 128 {
+            return Err(eyre!("{}x{} platform does not fit in u128", width, height));
+        }
+        let mut rows = [0; 128];
+        let mut cubes = [0; 128];
+        for (y, l) in s.lines().enumerate() {
+            if l.len() != width {
+                return Err(eyre!("line {} is not {} tiles wide", y + 1, width));
+            }
+            let (mut round, mut cube, mut bit) = (0, 0, 1);
+            for c in l.bytes() {
+                match c {
+                    b'O' => round |= bit,
+                    b'#' => cube |= bit,
+                    b'.' => {}
+                    _ => return Err(eyre!("invalid tile {:?}", c as char)),
+                }
+                bit <<= 1;
+            }
+            rows[y] = round;
+            cubes[y] = cube;
+        }
+        let walls = |cubes: &[u128], len: usize| -> Vec<u128> {
+            cubes
+                .iter()
+                .map(|l| l | !(u128::MAX >> (128 - len)))
+                .collect()
+        };
+        let row_walls = walls(&cubes[..height], width);
+        transpose(&mut cubes);
+        let mut cols = rows;
+        transpose(&mut cols);
 
-    // find mu, the start of the cycle
-    let mut mu = 0;
-    tortoise = tilt.clone();
-    while tortoise != hare {
-        cycle(&mut tortoise);
-        cycle(&mut hare);
-        mu += 1;
+        let by_col = TiltCache::new(width, height);
+        let by_row = TiltCache::new(height, width);
+        Ok(Platform {
+            row_walls,
+            col_walls: walls(&cubes[..width], height),
+            rows: rows[..height].to_vec(),
+            cols: cols[..width].to_vec(),
+            caches: vec![by_col.clone(), by_row.clone(), by_col, by_row],
+        })
     }
+}
 
-    // find the cycle len
-    let mut cycle_len = 1;
-    let mut hare = tortoise.clone();
-    cycle(&mut hare);
-    while tortoise != hare {
-        cycle(&mut hare);
-        cycle_len += 1;
+impl Platform {
+    /// Moves every round rock of a segment to its end in a single fill
+    fn tilt(&mut self, dir: Dir) {
+        let (lines, other, walls) = match dir {
+            Dir::North | Dir::South => (&mut self.cols, &mut self.rows, &self.col_walls),
+            Dir::West | Dir::East => (&mut self.rows, &mut self.cols, &self.row_walls),
+        };
+        let cache = &mut self.caches[dir as usize];
+
+        let to_start = matches!(dir, Dir::North | Dir::West);
+        let lines = lines.iter_mut().zip(walls).zip(&mut cache.input);
+        for (i, ((line, line_walls), input)) in lines.enumerate() {
+            // only segments whose rocks changed since last time get refilled
+            let mut changed = *line ^ *input;
+            if changed == 0 {
+                *line = cache.output[i];
+                continue;
+            }
+            *input = *line;
+            let mut res = cache.output[i];
+            while changed != 0 {
+                let seg = Segment::around(*line_walls, changed.trailing_zeros() as usize);
+                changed &= !seg.mask;
+                res &= !seg.mask;
+                let n = (*line & seg.mask).count_ones() as usize;
+                if n == 0 {
+                    continue;
+                }
+                let fill = u128::MAX >> (128 - n);
+                res |= if to_start {
+                    fill << seg.start
+                } else {
+                    fill << (seg.end - n)
+                };
+            }
+
+            let mut moved = res ^ cache.output[i];
+            while moved != 0 {
+                let j = moved.trailing_zeros() as usize;
+                cache.transposed[j] ^= 1 << i;
+                moved &= moved - 1;
+            }
+            cache.output[i] = res;
+            *line = res;
+        }
+        other.copy_from_slice(&cache.transposed);
     }
 
-    let remaining = (1000000000 - mu) % cycle_len;
-    for _ in 0..remaining {
-        cycle(&mut tortoise);
+    /// Each round rock weighs its distance to the opposite edge of `side`
+    fn load(&self, side: Dir) -> usize {
+        let lines = match side {
+            Dir::North | Dir::South => &self.rows,
+            Dir::West | Dir::East => &self.cols,
+        };
+        let len = lines.len();
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                let weight = match side {
+                    Dir::North | Dir::West => len - i,
+                    Dir::South | Dir::East => i + 1,
+                };
+                l.count_ones() as usize * weight
+            })
+            .sum()
     }
 
-    let mut total = 0;
-    for (li, l) in tortoise.split(|&c| c == b'\n').enumerate() {
-        total += l.iter().filter(|&c| *c == b'O').count() * (height - li);
+    /// Applies `seq` `count` times, skipping ahead once a state repeats
+    fn spin(&mut self, seq: &[Dir], count: usize, side: Dir) -> Spin {
+        // growing the map would hash every state again, they tend to repeat
+        // within a few hundred cycles
+        let mut seen: HashMap<Vec<u128>, usize> = HashMap::with_capacity(count.min(512) + 1);
+        let mut loads = vec![];
+        let (prefix, period) = loop {
+            let i = loads.len();
+            if let Some(prev) = seen.insert(self.rows.clone(), i) {
+                break (prev, i - prev);
+            }
+            loads.push(self.load(side));
+            for dir in seq {
+                self.tilt(*dir);
+            }
+        };
+
+        let i = if count < prefix {
+            count
+        } else {
+            prefix + (count - prefix) % period
+        };
+        Spin {
+            load: loads[i],
+            prefix,
+            period,
+        }
     }
-    Ok(total)
+}
+
+fn part1(input: &str) -> Result<usize> {
+    let mut platform: Platform = input.parse()?;
+    platform.tilt(Dir::North);
+    Ok(platform.load(Dir::North))
+}
+
+fn part2(input: &str) -> Result<usize> {
+    let mut platform: Platform = input.parse()?;
+    let spin = platform.spin(&parse_dirs("NWSE")?, 1000000000, Dir::North);
+    Ok(spin.load)
+}
+
+/// `cargo run -- <tilts> <count> <load side>`, e.g. `cargo run -- NN 5 S`
+fn query(input: &str, args: &[String]) -> Result<()> {
+    let [seq, count, side] = args else {
+        return Err(eyre!("usage: <tilts> <count> <load side>"));
+    };
+    let mut platform: Platform = input.parse()?;
+    let seq = parse_dirs(seq)?;
+    let side = parse_dirs(side)?;
+    let [side] = side[..] else {
+        return Err(eyre!("load side is a single direction"));
+    };
+    let spin = platform.spin(&seq, count.parse()?, side);
+    println!(
+        "[*] load: {} (prefix {}, period {})",
+        spin.load, spin.prefix, spin.period
+    );
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -105,6 +294,11 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return query(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
@@ -0,0 +1,319 @@
+use std::{collections::HashSet, str::FromStr};
+
+use color_eyre::eyre::{ContextCompat, Error, Result};
+use rayon::prelude::*;
+
+/// Right, left, down, up
+pub const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+pub const GLYPHS: [u8; 4] = [b'>', b'<', b'v', b'^'];
+
+/// Beam entering `cell` going towards `DIRECTIONS[dir]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Beam {
+    pub cell: usize,
+    pub dir: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Contraption {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u8>,
+}
+
+impl FromStr for Contraption {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let height = s.lines().count();
+        let width = s.lines().next().wrap_err("empty contraption")?.len();
+        let tiles = s.lines().flat_map(|l| l.bytes()).collect();
+        Ok(Contraption {
+            width,
+            height,
+            tiles,
+        })
+    }
+}
+
+/// Direction a beam leaves a tile with, `None` when a splitter splits it
+fn turn(tile: u8, dir: usize) -> Option<usize> {
+    match tile {
+        b'\\' => Some([2, 3, 0, 1][dir]),
+        b'/' => Some([3, 2, 1, 0][dir]),
+        b'-' if dir >= 2 => None,
+        b'|' if dir <= 1 => None,
+        _ => Some(dir),
+    }
+}
+
+fn split_dirs(tile: u8) -> [usize; 2] {
+    match tile {
+        b'-' => [0, 1],
+        b'|' => [2, 3],
+        _ => unreachable!(),
+    }
+}
+
+/// Straight run of a beam until it leaves the grid, loops or hits a splitter
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub beams: Vec<Beam>,
+    pub tiles: Vec<u64>,
+    /// Splitter cell the beam ends on
+    pub end: Option<usize>,
+}
+
+impl Contraption {
+    pub fn step(&self, cell: usize, dir: usize) -> Option<usize> {
+        let (dx, dy) = DIRECTIONS[dir];
+        let x = (cell % self.width) as isize + dx;
+        let y = (cell / self.width) as isize + dy;
+        if x < 0 || x >= self.width as isize || y < 0 || y >= self.height as isize {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    fn words(&self) -> usize {
+        self.tiles.len().div_ceil(64)
+    }
+
+    fn segment(&self, start: Option<Beam>) -> Segment {
+        let mut segment = Segment {
+            beams: vec![],
+            tiles: vec![0; self.words()],
+            end: None,
+        };
+        let mut seen = HashSet::new();
+        let mut beam = start;
+        while let Some(b) = beam {
+            if !seen.insert(b) {
+                break;
+            }
+            segment.beams.push(b);
+            segment.tiles[b.cell / 64] |= 1 << (b.cell % 64);
+            let Some(dir) = turn(self.tiles[b.cell], b.dir) else {
+                segment.end = Some(b.cell);
+                break;
+            };
+            beam = self.step(b.cell, dir).map(|cell| Beam { cell, dir });
+        }
+        segment
+    }
+
+    /// Every beam entering from an edge of the grid
+    pub fn entries(&self) -> Vec<Beam> {
+        let (w, h) = (self.width, self.height);
+        let mut res = vec![];
+        for x in 0..w {
+            res.push(Beam { cell: x, dir: 2 });
+            res.push(Beam {
+                cell: (h - 1) * w + x,
+                dir: 3,
+            });
+        }
+        for y in 0..h {
+            res.push(Beam {
+                cell: y * w,
+                dir: 0,
+            });
+            res.push(Beam {
+                cell: y * w + w - 1,
+                dir: 1,
+            });
+        }
+        res
+    }
+}
+
+/// Splitters linked by the segments between them. Splitters in the same
+/// strongly connected component energize the same tiles, so those are
+/// computed once per component.
+pub struct BeamGraph {
+    pub contraption: Contraption,
+    /// Node of each splitter cell
+    pub nodes: Vec<Option<usize>>,
+    /// Both segments leaving each splitter
+    pub outgoing: Vec<[Segment; 2]>,
+    pub scc: Vec<usize>,
+    /// Tiles energized from each component
+    pub reach: Vec<Vec<u64>>,
+}
+
+struct Tarjan<'a> {
+    edges: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    scc: Vec<usize>,
+    count: usize,
+}
+
+impl Tarjan<'_> {
+    /// Components are numbered in reverse topological order
+    fn visit(&mut self, v: usize) {
+        self.index[v] = Some(self.next);
+        self.low[v] = self.next;
+        self.next += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+
+        for &w in self.edges[v].iter() {
+            match self.index[w] {
+                None => {
+                    self.visit(w);
+                    self.low[v] = self.low[v].min(self.low[w]);
+                }
+                Some(i) if self.on_stack[w] => self.low[v] = self.low[v].min(i),
+                _ => {}
+            }
+        }
+
+        if Some(self.low[v]) == self.index[v] {
+            while let Some(w) = self.stack.pop() {
+                self.on_stack[w] = false;
+                self.scc[w] = self.count;
+                if w == v {
+                    break;
+                }
+            }
+            self.count += 1;
+        }
+    }
+}
+
+fn union(a: &mut [u64], b: &[u64]) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x |= y;
+    }
+}
+
+impl BeamGraph {
+    pub fn new(contraption: Contraption) -> Self {
+        let mut nodes = vec![None; contraption.tiles.len()];
+        let mut cells = vec![];
+        for (i, t) in contraption.tiles.iter().enumerate() {
+            if matches!(t, b'-' | b'|') {
+                nodes[i] = Some(cells.len());
+                cells.push(i);
+            }
+        }
+
+        let outgoing: Vec<[Segment; 2]> = cells
+            .iter()
+            .map(|c| {
+                split_dirs(contraption.tiles[*c]).map(|dir| {
+                    let start = contraption.step(*c, dir).map(|cell| Beam { cell, dir });
+                    contraption.segment(start)
+                })
+            })
+            .collect();
+        let edges: Vec<Vec<usize>> = outgoing
+            .iter()
+            .map(|segs| segs.iter().filter_map(|s| nodes[s.end?]).collect())
+            .collect();
+
+        let n = cells.len();
+        let mut tarjan = Tarjan {
+            edges: &edges,
+            index: vec![None; n],
+            low: vec![0; n],
+            stack: vec![],
+            on_stack: vec![false; n],
+            next: 0,
+            scc: vec![0; n],
+            count: 0,
+        };
+        for v in 0..n {
+            if tarjan.index[v].is_none() {
+                tarjan.visit(v);
+            }
+        }
+        let (scc, count) = (tarjan.scc, tarjan.count);
+
+        let mut members = vec![vec![]; count];
+        for v in 0..n {
+            members[scc[v]].push(v);
+        }
+        let mut reach: Vec<Vec<u64>> = vec![vec![0; contraption.words()]; count];
+        for c in 0..count {
+            let mut tiles = vec![0; contraption.words()];
+            for &v in members[c].iter() {
+                tiles[cells[v] / 64] |= 1 << (cells[v] % 64);
+                for seg in outgoing[v].iter() {
+                    union(&mut tiles, &seg.tiles);
+                }
+                // successors outside the component come earlier
+                for &w in edges[v].iter() {
+                    if scc[w] != c {
+                        union(&mut tiles, &reach[scc[w]]);
+                    }
+                }
+            }
+            reach[c] = tiles;
+        }
+
+        BeamGraph {
+            contraption,
+            nodes,
+            outgoing,
+            scc,
+            reach,
+        }
+    }
+
+    pub fn energized(&self, start: Beam) -> usize {
+        let seg = self.contraption.segment(Some(start));
+        let mut tiles = seg.tiles;
+        if let Some(node) = seg.end.and_then(|c| self.nodes[c]) {
+            union(&mut tiles, &self.reach[self.scc[node]]);
+        }
+        tiles.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Grid with energized tiles drawn as `#`, or as the beam direction
+    /// when a single one crosses them
+    pub fn overlay(&self, start: Beam) -> String {
+        let c = &self.contraption;
+        let mut dirs = vec![0u8; c.tiles.len()];
+        let seg = c.segment(Some(start));
+        let mut segments = vec![&seg];
+        let mut seen = vec![false; self.outgoing.len()];
+        while let Some(seg) = segments.pop() {
+            for b in seg.beams.iter() {
+                dirs[b.cell] |= 1 << b.dir;
+            }
+            if let Some(node) = seg.end.and_then(|c| self.nodes[c]) {
+                if !std::mem::replace(&mut seen[node], true) {
+                    segments.extend(self.outgoing[node].iter());
+                }
+            }
+        }
+
+        let mut s = String::new();
+        for (i, d) in dirs.iter().enumerate() {
+            if i > 0 && i % c.width == 0 {
+                s.push('\n');
+            }
+            let glyph = match d.count_ones() {
+                0 => c.tiles[i],
+                1 => GLYPHS[d.trailing_zeros() as usize],
+                _ => b'#',
+            };
+            s.push(glyph as char);
+        }
+        s
+    }
+
+    /// Energized tiles for every entry on the edges
+    pub fn scan(&self) -> Vec<(Beam, usize)> {
+        self.contraption
+            .entries()
+            .into_par_iter()
+            .map(|e| (e, self.energized(e)))
+            .collect()
+    }
+}
@@ -1,121 +1,68 @@
-use std::{collections::HashSet, time::Instant};
+mod beams;
 
-use color_eyre::eyre::Result;
+use std::time::Instant;
 
-fn solve_with_start(input: &[u8], width: isize, height: isize, start: (isize, usize)) -> usize {
-    const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-
-    let mut queue: Vec<(isize, usize)> = Vec::new();
-    queue.push(start);
-    let mut energized = HashSet::new();
-    let mut visited = HashSet::new();
-
-    let mut update_pos = |queue: &mut Vec<(isize, usize)>,
-                          row: &mut isize,
-                          col: &mut isize,
-                          dir_i: usize,
-                          push: bool| {
-        if *row >= 0 || *row < width || *col >= 0 || *col < height {
-            energized.insert(*col * width + *row);
-        }
-        let dir = DIRECTIONS[dir_i];
-        *row += dir.0;
-        *col += dir.1;
-
-        let c = *col * width + *row;
-        if push && !visited.contains(&(c, dir_i)) {
-            visited.insert((c, dir_i));
-            queue.push((c, dir_i));
-        }
-    };
-
-    while let Some(e) = queue.pop() {
-        let (ci, dir_i) = e;
-        let mut row = ci % width;
-        let mut col = ci / width;
-
-        while input.get((col * width + row) as usize) == Some(&b'.') {
-            update_pos(&mut queue, &mut row, &mut col, dir_i, false);
-        }
-        match input.get((col * width + row) as usize) {
-            Some(b'\\') => {
-                let new_dir = match dir_i {
-                    0 => 2,
-                    1 => 3,
-                    2 => 0,
-                    3 => 1,
-                    _ => unreachable!(),
-                };
-                update_pos(&mut queue, &mut row, &mut col, new_dir, true);
-            }
-            Some(b'/') => {
-                let new_dir = match dir_i {
-                    0 => 3,
-                    1 => 2,
-                    2 => 1,
-                    3 => 0,
-                    _ => unreachable!(),
-                };
-                update_pos(&mut queue, &mut row, &mut col, new_dir, true);
-            }
-            Some(b'-') => {
-                if dir_i >= 2 {
-                    let mut r1 = row;
-                    let mut c1 = col;
-                    update_pos(&mut queue, &mut r1, &mut c1, 0, true);
-                    let mut r1 = row;
-                    let mut c1 = col;
-                    update_pos(&mut queue, &mut r1, &mut c1, 1, true);
-                } else {
-                    update_pos(&mut queue, &mut row, &mut col, dir_i, true);
-                }
-            }
-            Some(b'|') => {
-                if dir_i <= 1 {
-                    let mut r1 = row;
-                    let mut c1 = col;
-                    update_pos(&mut queue, &mut r1, &mut c1, 2, true);
-
-                    let mut r1 = row;
-                    let mut c1 = col;
-                    update_pos(&mut queue, &mut r1, &mut c1, 3, true);
-                } else {
-                    update_pos(&mut queue, &mut row, &mut col, dir_i, true);
-                }
-            }
-            None => {}
-            Some(b'\n') => {}
-            Some(_) => unreachable!(),
-        }
-    }
-    energized.len()
-}
+use beams::{Beam, BeamGraph, Contraption, GLYPHS};
+use color_eyre::eyre::{eyre, ContextCompat, Result};
 
 fn part1(input: &str) -> Result<usize> {
-    let height = input.lines().count() as isize;
-    let width = input.lines().next().unwrap().len() as isize + 1;
-    let s = solve_with_start(input.as_bytes(), width, height, (0, 0));
-    Ok(s)
+    let graph = BeamGraph::new(input.parse::<Contraption>()?);
+    Ok(graph.energized(Beam { cell: 0, dir: 0 }))
 }
 
 fn part2(input: &str) -> Result<usize> {
-    let height = input.lines().count() as isize;
-    let width = input.lines().next().unwrap().len() as isize + 1;
+    let graph = BeamGraph::new(input.parse::<Contraption>()?);
+    let best = graph
+        .scan()
+        .into_iter()
+        .map(|(_, n)| n)
+        .max()
+        .wrap_err("no entry")?;
+    Ok(best)
+}
 
-    let mut best = 0;
-    for i in 0..width {
-        let s = solve_with_start(input.as_bytes(), width, height, (i, 2));
-        best = best.max(s);
-        let s = solve_with_start(input.as_bytes(), width, height, (height * width - i, 3));
-        best = best.max(s);
+/// Entry beam written as `x,y,dir`, e.g. `0,0,>`
+fn parse_entry(c: &Contraption, s: &str) -> Result<Beam> {
+    let mut v = s.split(',');
+    let x: usize = v.next().wrap_err("no x")?.parse()?;
+    let y: usize = v.next().wrap_err("no y")?.parse()?;
+    let glyph = v.next().wrap_err("no direction")?.as_bytes();
+    let dir = GLYPHS
+        .iter()
+        .position(|g| glyph == [*g])
+        .wrap_err("direction is one of > < v ^")?;
+    if x >= c.width || y >= c.height {
+        return Err(eyre!("{},{} is outside the grid", x, y));
     }
-    for i in 0..height {
-        let s = solve_with_start(input.as_bytes(), width, height, (width * i, 0));
-        best = best.max(s);
-        let s = solve_with_start(input.as_bytes(), width, height, (width * (i + 1) - 2, 1));
-        best = best.max(s);
+    Ok(Beam {
+        cell: y * c.width + x,
+        dir,
+    })
+}
+
+fn query(input: &str, args: &[String]) -> Result<()> {
+    let graph = BeamGraph::new(input.parse::<Contraption>()?);
+    let c = &graph.contraption;
+    match args {
+        [cmd, rest @ ..] if cmd == "--overlay" && rest.len() <= 1 => {
+            let start = match rest.first() {
+                Some(entry) => parse_entry(c, entry)?,
+                None => Beam { cell: 0, dir: 0 },
+            };
+            println!("{}", graph.overlay(start));
+            println!("[*] energized: {}", graph.energized(start));
+        }
+        [cmd] if cmd == "--entries" => {
+            let mut scan = graph.scan();
+            scan.sort_by_key(|(e, n)| (std::cmp::Reverse(*n), e.cell, e.dir));
+            for (e, n) in scan {
+                let (x, y) = (e.cell % c.width, e.cell / c.width);
+                println!("{},{},{}\t{}", x, y, GLYPHS[e.dir] as char, n);
+            }
+        }
+        _ => return Err(eyre!("usage: --overlay [x,y,dir] | --entries")),
     }
-    Ok(best)
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -123,6 +70,11 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return query(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
@@ -1,11 +1,6 @@
-use std::{
-    borrow::Borrow,
-    cell::{Ref, RefCell},
-    collections::{BinaryHeap, HashMap},
-    time::Instant,
-};
+use std::{str::FromStr, time::Instant};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 enum Direction {
@@ -15,281 +10,335 @@ enum Direction {
     West,
 }
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+
 impl Direction {
-    pub fn dirs(&self) -> [(i32, i32, Direction); 3] {
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    pub fn glyph(&self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::South => 'v',
+            Direction::East => '>',
+            Direction::West => '<',
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
         match self {
-            Direction::North => [
-                (0, -1, Direction::North),
-                (-1, 0, Direction::West),
-                (1, 0, Direction::East),
-            ],
-            Direction::South => [
-                (0, 1, Direction::South),
-                (-1, 0, Direction::West),
-                (1, 0, Direction::East),
-            ],
-            Direction::East => [
-                (1, 0, Direction::East),
-                (0, -1, Direction::North),
-                (0, 1, Direction::South),
-            ],
-            Direction::West => [
-                (-1, 0, Direction::West),
-                (0, -1, Direction::North),
-                (0, 1, Direction::South),
-            ],
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
+/// Rules a crucible moves by
+#[derive(Debug, Clone, Copy)]
+pub struct Crucible {
+    /// Blocks to move straight before turning or stopping
+    pub min_run: u32,
+    /// Blocks it can move straight at most
+    pub max_run: u32,
+    /// Heat lost on top of the block's one for each turn
+    pub turn_penalty: u32,
+    /// Whether it can go back where it came from
+    pub reverse: bool,
+    /// `(x, y)` cell it starts from
+    pub start: (u32, u32),
+    /// `(x, y)` cell it must reach, defaults to the bottom right corner
+    pub target: Option<(u32, u32)>,
+}
+
+impl Crucible {
+    pub fn regular() -> Self {
+        Crucible {
+            min_run: 1,
+            max_run: 3,
+            turn_penalty: 0,
+            reverse: false,
+            start: (0, 0),
+            target: None,
+        }
+    }
+
+    pub fn ultra() -> Self {
+        Crucible {
+            min_run: 4,
+            max_run: 10,
+            ..Crucible::regular()
         }
     }
 }
 
+/// Crucible on `(x, y)`, having moved `count` blocks towards `direction`.
+/// A count of 0 means it has not moved yet and can go anywhere.
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub struct Node {
-    row: u32,
-    col: u32,
+    x: u32,
+    y: u32,
     direction: Direction,
     count: u32,
 }
 
-type Cache = Vec<Option<Vec<Node>>>;
-
-pub struct Graph<'a> {
-    input: &'a [u8],
+pub struct Graph {
+    /// Heat loss of each block
+    blocks: Vec<u8>,
     width: u32,
     height: u32,
-    max_count: u32,
-    min_count: u32,
-}
-
-#[derive(Debug, Eq, PartialEq)]
-pub struct QueueEntry {
-    real_cost: u32,
-    heur_cost: u32,
-    node: Node,
+    rules: Crucible,
 }
 
-impl PartialOrd for QueueEntry {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl FromStr for Graph {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let width = s.lines().next().wrap_err("empty map")?.len() as u32;
+        let height = s.lines().count() as u32;
+        let blocks = s
+            .lines()
+            .flat_map(|l| l.bytes())
+            .map(|b| match b {
+                b'1'..=b'9' => Ok(b - b'0'),
+                _ => Err(eyre!("invalid block {:?}", b as char)),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Graph {
+            blocks,
+            width,
+            height,
+            rules: Crucible::regular(),
+        })
     }
 }
 
-impl Ord for QueueEntry {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // NOTE: we reverse the order to get smaller cost at the top
-        other.heur_cost.cmp(&self.heur_cost)
-    }
-}
-
-impl Graph<'_> {
+impl Graph {
     #[inline]
-    pub fn idx(&self, row: u32, col: u32) -> u32 {
-        col * self.width + row
+    pub fn idx(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
     }
 
+    /// Dense index of a node, counts go from 0 to `max_run`
     #[inline]
     pub fn eidx(&self, node: Node) -> usize {
-        ((self.idx(node.row, node.col) * 4 * self.max_count)
-            + (node.direction as u32 * 4)
-            + node.count) as usize
+        let runs = self.rules.max_run as usize + 1;
+        (self.idx(node.x, node.y) * 4 + node.direction as usize) * runs + node.count as usize
     }
 
-    pub fn edges(&mut self, node: Node, edge_cache: &mut Cache) -> usize {
-        let i = self.eidx(node);
-        if edge_cache[i].is_some() {
-            return i;
-        }
+    pub fn states(&self) -> usize {
+        (self.width * self.height) as usize * 4 * (self.rules.max_run as usize + 1)
+    }
 
-        let mut res = Vec::new();
-        for dir in node.direction.dirs().iter() {
-            if dir.2 == node.direction && node.count + 1 > self.max_count {
-                continue;
-            }
-            if dir.2 != node.direction && node.count < self.min_count {
-                continue;
+    pub fn target(&self) -> (u32, u32) {
+        self.rules
+            .target
+            .unwrap_or((self.width - 1, self.height - 1))
+    }
+
+    fn contains(&self, (x, y): (u32, u32)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Nodes reachable in one move along with the heat lost
+    pub fn edges(&self, node: Node) -> impl Iterator<Item = (Node, u32)> + '_ {
+        DIRECTIONS.into_iter().filter_map(move |dir| {
+            let rules = &self.rules;
+            let moving = node.count > 0;
+            let straight = dir == node.direction;
+            if moving {
+                if straight && node.count >= rules.max_run {
+                    return None;
+                }
+                if !straight && node.count < rules.min_run {
+                    return None;
+                }
+                if dir == node.direction.opposite() && !rules.reverse {
+                    return None;
+                }
             }
-            let new_row = node.row as i32 + dir.0;
-            let new_col = node.col as i32 + dir.1;
-            if new_row >= 0
-                && new_row < self.width as i32 - 1
-                && new_col >= 0
-                && new_col < self.height as i32
-            {
-                let count = if dir.2 == node.direction {
-                    node.count + 1
-                } else {
-                    1
-                };
-                res.push(Node {
-                    row: new_row as u32,
-                    col: new_col as u32,
-                    direction: dir.2,
-                    count,
-                });
+            let (dx, dy) = dir.delta();
+            let x = node.x.checked_add_signed(dx)?;
+            let y = node.y.checked_add_signed(dy)?;
+            if !self.contains((x, y)) {
+                return None;
             }
-        }
 
-        edge_cache[i] = Some(res);
-        i
+            let mut cost = self.blocks[self.idx(x, y)] as u32;
+            let count = if moving && straight {
+                node.count + 1
+            } else {
+                if moving {
+                    cost += rules.turn_penalty;
+                }
+                1
+            };
+            let next = Node {
+                x,
+                y,
+                direction: dir,
+                count,
+            };
+            Some((next, cost))
+        })
     }
 }
 
-const STARTS: [Node; 2] = [
-    Node {
-        row: 0,
-        col: 0,
+/// Heat lost and nodes walked from the start to the target
+pub fn astar(graph: &Graph, heuristic: impl Fn(&Graph, Node) -> u32) -> Option<(u32, Vec<Node>)> {
+    let rules = graph.rules;
+    if !graph.contains(rules.start) || !graph.contains(graph.target()) {
+        return None;
+    }
+    let start = Node {
+        x: rules.start.0,
+        y: rules.start.1,
         direction: Direction::East,
         count: 0,
-    },
-    Node {
-        row: 0,
-        col: 0,
-        direction: Direction::South,
-        count: 0,
-    },
-];
-
-pub fn astar(
-    graph: &mut Graph,
-    starts: &[Node],
-    target: Node,
-    heuristic: impl Fn(&Graph, Node) -> u32,
-) -> Option<(u32, Vec<Node>)> {
-    let mut prevs: HashMap<Node, (Option<Node>, i32)> = HashMap::new();
-    //let mut queue = BinaryHeap::new();
-    let mut queue = (0..100)
-        .map(|_| Vec::with_capacity(1000))
-        .collect::<Vec<_>>();
-
-    for start in starts {
-        /*
-        queue.push(QueueEntry {
-            real_cost: 0,
-            heur_cost: heuristic(graph, *start),
-            node: *start,
-        });
-        */
-        queue[0].push(QueueEntry {
-            real_cost: 0,
-            heur_cost: heuristic(graph, *start),
-            node: *start,
-        });
-        prevs.insert(*start, (None, 0));
-    }
-
-    let mut edge_cache = vec![None; (graph.width * graph.height * 4 * graph.max_count) as usize];
-    let mut prevs2 = vec![None; (graph.width * graph.height * 4 * graph.max_count) as usize];
-    let mut found = None;
-
-    let mut bucket = 0;
+    };
 
-    //while let Some(e) = queue.pop() {
-    'l: loop {
-        while let Some(e) = queue[bucket % 100].pop() {
-            if e.node.row == target.row && e.node.col == target.col && e.node.count >= graph.min_count {
-                found = Some((e.node, e.real_cost));
-                break 'l;
+    // estimates grow by at most the costliest move plus one at each step
+    let buckets = (9 + rules.turn_penalty + 2) as usize;
+    let mut queue = vec![Vec::with_capacity(1000); buckets];
+    let mut costs = vec![u32::MAX; graph.states()];
+    let mut prevs: Vec<Option<Node>> = vec![None; graph.states()];
+    let mut pending = 1;
+    queue[heuristic(graph, start) as usize % buckets].push((0, start));
+    costs[graph.eidx(start)] = 0;
+
+    let target = graph.target();
+    let mut bucket = heuristic(graph, start) as usize;
+    while pending > 0 {
+        while let Some((cost, node)) = queue[bucket % buckets].pop() {
+            pending -= 1;
+            if cost > costs[graph.eidx(node)] {
+                continue;
             }
-            let ei = graph.edges(e.node, &mut edge_cache);
-            for edge in edge_cache[ei].as_ref().unwrap() {
-                let c = graph.input[graph.idx(edge.row, edge.col) as usize];
-                // little trick to convert ascii to number
-                let new_rcost = e.real_cost + (c - 48) as u32;
-
-                if prevs2[graph.eidx(*edge)].map_or(true, |c| new_rcost < c) {
-                //if prevs.get(edge).map_or(true, |(_, c)| new_rcost < *c) {
-                    //prevs.insert(*edge, (Some(e.node), new_rcost));
-                    prevs2[graph.eidx(*edge)] = Some(new_rcost);
-
-                    let new_hcost = new_rcost + heuristic(graph, *edge);
-
-                    queue[new_hcost as usize % 100].push(QueueEntry {
-                        real_cost: new_rcost,
-                        heur_cost: new_hcost,
-                        node: *edge,
-                    });
-                    /*
-                    queue.push(QueueEntry {
-                        real_cost: new_rcost,
-                        heur_cost: new_hcost,
-                        node: *edge,
-                    });
-                    */
+            if (node.x, node.y) == target && node.count >= rules.min_run {
+                let mut path = vec![node];
+                let mut cur = node;
+                while let Some(prev) = prevs[graph.eidx(cur)] {
+                    path.push(prev);
+                    cur = prev;
+                }
+                path.reverse();
+                return Some((cost, path));
+            }
+            for (edge, heat) in graph.edges(node) {
+                let new_cost = cost + heat;
+                let ei = graph.eidx(edge);
+                if new_cost < costs[ei] {
+                    costs[ei] = new_cost;
+                    prevs[ei] = Some(node);
+                    let estimate = new_cost + heuristic(graph, edge);
+                    queue[estimate as usize % buckets].push((new_cost, edge));
+                    pending += 1;
                 }
             }
         }
         bucket += 1;
     }
+    None
+}
 
-    // reconstruct path
-    let (target_id, target_cost) = found?;
-    let mut path = vec![target_id];
-    /*
-    let mut cur = target_id;
-    while !starts.contains(&cur) {
-        let (prev_node, _) = prevs[&cur];
-        cur = prev_node?;
-        path.push(cur);
+/// Direction and length of each straight run of a path
+fn runs(path: &[Node]) -> Vec<(Direction, u32)> {
+    let mut runs = vec![];
+    for node in path.iter().skip(1) {
+        if node.count == 1 {
+            runs.push((node.direction, 0));
+        }
+        if let Some(run) = runs.last_mut() {
+            run.1 += 1;
+        }
     }
-    path.reverse();
-    */
-    Some((target_cost, path))
+    runs
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let width = input.lines().next().unwrap().len() + 1;
-    let height = input.lines().count();
-    let mut g = Graph {
-        input: input.as_bytes(),
-        width: width as u32,
-        height: height as u32,
-        max_count: 3,
-        min_count: 0,
-    };
-    let end = Node {
-        row: g.width - 2,
-        col: g.height - 1,
-        direction: Direction::South,
-        count: 0,
-    };
-    let res = astar(&mut g, &STARTS, end, |_, _| 0);
-    let (cost, _) = res.unwrap();
+impl Graph {
+    /// Heat loss map with the path drawn over it
+    fn render(&self, path: &[Node]) -> String {
+        let mut cells: Vec<char> = self.blocks.iter().map(|b| (b'0' + b) as char).collect();
+        for node in path.iter().skip(1) {
+            cells[self.idx(node.x, node.y)] = node.direction.glyph();
+        }
+        cells
+            .chunks(self.width as usize)
+            .map(|l| l.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
 
+fn solve(input: &str, rules: Crucible) -> Result<(Graph, u32, Vec<Node>)> {
+    let mut g: Graph = input.parse()?;
+    g.rules = rules;
+    let (tx, ty) = g.target();
+    // manhattan distance, each block loses at least 1 heat
+    let res = astar(&g, |_, node| node.x.abs_diff(tx) + node.y.abs_diff(ty));
+    let (cost, path) = res.wrap_err("target is unreachable")?;
+    Ok((g, cost, path))
+}
+
+fn part1(input: &str) -> Result<usize> {
+    let (_, cost, _) = solve(input, Crucible::regular())?;
     Ok(cost as usize)
 }
 
 fn part2(input: &str) -> Result<usize> {
-    let width = input.lines().next().unwrap().len() + 1;
-    let height = input.lines().count();
-    let mut g = Graph {
-        input: input.as_bytes(),
-        width: width as u32,
-        height: height as u32,
-        max_count: 10,
-        min_count: 4,
-    };
-    let end = Node {
-        row: g.width - 2,
-        col: g.height - 1,
-        direction: Direction::South, // not used
-        count: 0,
-    };
-    let res = astar(
-        &mut g,
-        &STARTS,
-        end,
-        // manhattan distance
-        //|_, node| (width as u32 - 2 - node.row) + (height as u32 - 1 - node.col),
-        |_, _| 0,
-    );
-    let (cost, _) = res.unwrap();
+    let (_, cost, _) = solve(input, Crucible::ultra())?;
     Ok(cost as usize)
 }
 
+fn print_routes(input: &str) -> Result<()> {
+    for (name, rules) in [
+        ("regular", Crucible::regular()),
+        ("ultra", Crucible::ultra()),
+    ] {
+        let (g, cost, path) = solve(input, rules)?;
+        println!("{}", g.render(&path));
+        let runs = runs(&path);
+        let desc: Vec<String> = runs
+            .iter()
+            .map(|(dir, n)| format!("{}{}", dir.glyph(), n))
+            .collect();
+        println!(
+            "[*] {} crucible loses {} heat: {}",
+            name,
+            cost,
+            desc.join(" ")
+        );
+        if let Some((dir, n)) = runs
+            .iter()
+            .find(|(_, n)| *n < rules.min_run || *n > rules.max_run)
+        {
+            return Err(eyre!("run {}{} breaks the rules", dir.glyph(), n));
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let input = include_str!("../input");
 
+    if std::env::args().nth(1).as_deref() == Some("--route") {
+        return print_routes(input);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
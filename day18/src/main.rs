@@ -1,88 +1,50 @@
 use std::time::Instant;
 
-use aoc_utils::point::*;
 use color_eyre::eyre::Result;
+use common::polygon::{Dir, Polygon};
 
-fn parse1(input: &str) -> (Vec<Point>, usize) {
+fn parse1(input: &str) -> Vec<(Dir, u64)> {
     let mut res = Vec::with_capacity(1000);
-    let mut start = Point::new(0, 0);
-    res.push(start);
-    let mut np = 0;
     for l in input.lines() {
-        let mut s = l.split(" ");
+        let mut s = l.split(' ');
         let dir = match s.next().unwrap() {
-            "R" => RIGHT,
-            "L" => LEFT,
-            "U" => UP,
-            "D" => DOWN,
+            "R" => Dir::Right,
+            "L" => Dir::Left,
+            "U" => Dir::Up,
+            "D" => Dir::Down,
             _ => unreachable!(),
         };
-        let val = s.next().unwrap().parse::<i32>().unwrap();
-        start += dir * val;
-        np += val as usize;
-        res.push(start);
+        let val = s.next().unwrap().parse::<u64>().unwrap();
+        res.push((dir, val));
     }
-    (res, np)
+    res
 }
 
-fn parse2(input: &str) -> (Vec<Point>, usize) {
+fn parse2(input: &str) -> Vec<(Dir, u64)> {
     let mut res = Vec::with_capacity(1000);
-    let mut start = Point::new(0, 0);
-    res.push(start);
-    let mut np = 0;
     for l in input.lines() {
         let (_, hex) = l.split_once("(#").unwrap();
         let dir = match hex.as_bytes()[5] {
-            b'0' => RIGHT,
-            b'1' => DOWN,
-            b'2' => LEFT,
-            b'3' => UP,
+            b'0' => Dir::Right,
+            b'1' => Dir::Down,
+            b'2' => Dir::Left,
+            b'3' => Dir::Up,
             _ => unreachable!(),
         };
-        let val = i32::from_str_radix(&hex[..5], 16).unwrap();
-        start += dir * val;
-        np += val as usize;
-        res.push(start);
+        let val = u64::from_str_radix(&hex[..5], 16).unwrap();
+        res.push((dir, val));
     }
-    (res, np)
+    res
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let (points, np) = parse1(input);
-    let mut sum: i64 = 0;
-
-    // shoelace formula
-    for win in points.windows(2) {
-        let [p0, p1] = win else { unreachable!() };
-        sum = sum
-            .checked_add(((p0.y + p1.y) * (p0.x - p1.x)).into())
-            .unwrap()
-    }
-    let area = 0.5 * sum.abs() as f64;
-
-    // find number of interior points with pick's theorem
-    let i = area - (np as f64 / 2.0) + 1.0;
-
-    Ok(i as usize + np)
+fn part1(input: &str) -> Result<u64> {
+    let polygon = Polygon::from_steps(parse1(input));
+    Ok(polygon.enclosed_cells() as u64)
 }
 
-fn part2(input: &str) -> Result<usize> {
-    let (points, np) = parse2(input);
-    let mut sum: i64 = 0;
-
-    // shoelace formula
-    for win in points.windows(2) {
-        let [p0, p1] = win else { unreachable!() };
-        sum = sum
-            .checked_add((p0.y + p1.y) as i64 * (p0.x - p1.x) as i64)
-            .unwrap()
-    }
-    let area = 0.5 * sum.abs() as f64;
-
-    // find number of interior points with pick's theorem
-    let i = area - (np as f64 / 2.0) + 1.0;
-
-    Ok(i as usize + np)
+fn part2(input: &str) -> Result<u64> {
+    let polygon = Polygon::from_steps(parse2(input));
+    Ok(polygon.enclosed_cells() as u64)
 }
 
 fn main() -> Result<()> {
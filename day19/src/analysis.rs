@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre::Result;
+
+use crate::workflow::{Hyperbox, Rule, System, Target};
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// Rules no part within the bounds can fire, as (workflow, rule index)
+    pub dead_rules: Vec<(String, usize)>,
+    /// Workflows no part within the bounds can enter
+    pub unreachable: Vec<String>,
+}
+
+impl System {
+    fn sorted_names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.workflows.keys().collect();
+        names.sort();
+        names
+    }
+
+    /// Follows every part of `bx` from `start`, so a rule is only live if
+    /// some part can get past all the conditions leading to it
+    pub fn analyze(&self, start: &str, bx: Hyperbox) -> Result<Analysis> {
+        let mut reached = HashSet::from([start]);
+        let mut fired = HashSet::new();
+        self.walk(start, bx, &mut |name, i, _| {
+            fired.insert((name, i));
+            if let Target::Workflow(n) = &self.workflows[name][i].target {
+                reached.insert(n);
+            }
+        })?;
+
+        let mut res = Analysis::default();
+        for name in self.sorted_names() {
+            if !reached.contains(name.as_str()) {
+                res.unreachable.push(name.clone());
+                continue;
+            }
+            for i in 0..self.workflows[name].len() {
+                if !fired.contains(&(name.as_str(), i)) {
+                    res.dead_rules.push((name.clone(), i));
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Drops dead rules and unreachable workflows, then inlines every
+    /// workflow whose branches all lead to the same target
+    pub fn simplify(&self, start: &str, bx: Hyperbox) -> Result<System> {
+        let analysis = self.analyze(start, bx)?;
+        let mut workflows = self.workflows.clone();
+        for name in analysis.unreachable.iter() {
+            workflows.remove(name);
+        }
+        for (name, i) in analysis.dead_rules.iter().rev() {
+            let rules = workflows.get_mut(name).unwrap();
+            rules.remove(*i);
+            // nothing gets past the last live rule anymore
+            if *i == rules.len() {
+                rules.last_mut().unwrap().cond = None;
+            }
+        }
+
+        loop {
+            for rules in workflows.values_mut() {
+                // a condition sending to the same place as the fallback is useless
+                while rules.len() >= 2
+                    && rules[rules.len() - 2].target == rules[rules.len() - 1].target
+                {
+                    rules.remove(rules.len() - 2);
+                }
+            }
+
+            let Some((name, target)) = workflows
+                .iter()
+                .find(|(n, rules)| rules.len() == 1 && *n != start)
+                .map(|(n, rules)| (n.clone(), rules[0].target.clone()))
+            else {
+                break;
+            };
+            workflows.remove(&name);
+            let old = Target::Workflow(name);
+            for rule in workflows.values_mut().flatten() {
+                if rule.target == old {
+                    rule.target = target.clone();
+                }
+            }
+        }
+
+        let system = System {
+            categories: self.categories.clone(),
+            workflows,
+            parts: self.parts.clone(),
+        };
+        system.validate()?;
+        Ok(system)
+    }
+
+    fn rule_label(&self, rule: &Rule) -> String {
+        match &rule.cond {
+            Some(cond) => self.cond_str(cond),
+            None => "else".to_string(),
+        }
+    }
+
+    /// Workflow graph, edges are labelled with the rule condition
+    pub fn to_dot(&self) -> String {
+        let mut s = String::from("digraph workflows {\n");
+        s.push_str("    \"A\" [shape=doublecircle, color=green];\n");
+        s.push_str("    \"R\" [shape=doublecircle, color=red];\n");
+        for name in self.sorted_names() {
+            for rule in self.workflows[name].iter() {
+                s.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    name,
+                    rule.target,
+                    self.rule_label(rule)
+                ));
+            }
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    /// Binary decision tree rooted at `start`, one node per condition
+    pub fn decision_tree_dot(&self, start: &str) -> String {
+        let mut s = String::from("digraph tree {\n");
+        let mut next_id = 0;
+        self.tree_node(start, 0, &mut s, &mut next_id);
+        s.push_str("}\n");
+        s
+    }
+
+    fn tree_node(&self, name: &str, i: usize, s: &mut String, next_id: &mut usize) -> usize {
+        let rule = &self.workflows[name][i];
+        let Some(cond) = &rule.cond else {
+            return self.tree_target(&rule.target, s, next_id);
+        };
+        let id = *next_id;
+        *next_id += 1;
+        s.push_str(&format!(
+            "    n{} [label=\"{}: {}\"];\n",
+            id,
+            name,
+            self.cond_str(cond)
+        ));
+        let t = self.tree_target(&rule.target, s, next_id);
+        let f = self.tree_node(name, i + 1, s, next_id);
+        s.push_str(&format!("    n{} -> n{} [label=\"true\"];\n", id, t));
+        s.push_str(&format!("    n{} -> n{} [label=\"false\"];\n", id, f));
+        id
+    }
+
+    fn tree_target(&self, target: &Target, s: &mut String, next_id: &mut usize) -> usize {
+        let color = match target {
+            Target::Workflow(n) => return self.tree_node(n, 0, s, next_id),
+            Target::Accept => "green",
+            Target::Reject => "red",
+        };
+        let id = *next_id;
+        *next_id += 1;
+        s.push_str(&format!(
+            "    n{} [label=\"{}\", shape=box, color={}];\n",
+            id, target, color
+        ));
+        id
+    }
+}
@@ -0,0 +1,377 @@
+use std::{collections::HashMap, fmt, ops::Range, str::FromStr};
+
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl Op {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Eq => "=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    Accept,
+    Reject,
+    Workflow(String),
+}
+
+impl From<&str> for Target {
+    fn from(s: &str) -> Self {
+        match s {
+            "A" => Target::Accept,
+            "R" => Target::Reject,
+            n => Target::Workflow(n.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Accept => write!(f, "A"),
+            Target::Reject => write!(f, "R"),
+            Target::Workflow(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cond {
+    pub category: usize,
+    pub op: Op,
+    pub value: u64,
+}
+
+impl Cond {
+    /// Values satisfying the condition, `u64::MAX` itself is never included
+    pub fn range(&self) -> Range<u64> {
+        let v = self.value;
+        match self.op {
+            Op::Lt => 0..v,
+            Op::Le => 0..v.saturating_add(1),
+            Op::Gt => match v.checked_add(1) {
+                Some(start) => start..u64::MAX,
+                None => u64::MAX..u64::MAX,
+            },
+            Op::Ge => v..u64::MAX,
+            Op::Eq => v..v.saturating_add(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub cond: Option<Cond>,
+    pub target: Target,
+}
+
+/// Axis-aligned box, one range per category
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hyperbox {
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl Hyperbox {
+    pub fn new(dims: usize, bounds: Range<u64>) -> Self {
+        Hyperbox {
+            ranges: vec![bounds; dims],
+        }
+    }
+
+    pub fn volume(&self) -> Option<u128> {
+        self.ranges
+            .iter()
+            .try_fold(1u128, |acc, r| acc.checked_mul((r.end - r.start) as u128))
+    }
+
+    /// Splits the box into the part matching `cond` and the parts that don't
+    pub fn split(&self, cond: &Cond) -> (Option<Hyperbox>, Vec<Hyperbox>) {
+        let r = &self.ranges[cond.category];
+        let c = cond.range();
+        let with = |range: Range<u64>| {
+            let mut b = self.clone();
+            b.ranges[cond.category] = range;
+            b
+        };
+
+        let inside = r.start.max(c.start)..r.end.min(c.end);
+        let outside = [r.start..r.end.min(c.start), r.start.max(c.end)..r.end];
+        let t = (!inside.is_empty()).then(|| with(inside));
+        let f = outside
+            .into_iter()
+            .filter(|r| !r.is_empty())
+            .map(with)
+            .collect();
+        (t, f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct System {
+    pub categories: Vec<String>,
+    pub workflows: HashMap<String, Vec<Rule>>,
+    pub parts: Vec<Vec<u64>>,
+}
+
+fn category_idx(categories: &mut Vec<String>, name: &str) -> usize {
+    if let Some(i) = categories.iter().position(|c| c == name) {
+        return i;
+    }
+    categories.push(name.to_string());
+    categories.len() - 1
+}
+
+fn parse_cond(categories: &mut Vec<String>, s: &str) -> Result<Cond> {
+    let i = s
+        .find(['<', '>', '='])
+        .wrap_err_with(|| format!("no comparison in {}", s))?;
+    let (name, rest) = s.split_at(i);
+    let (op, value) = if let Some(v) = rest.strip_prefix("<=") {
+        (Op::Le, v)
+    } else if let Some(v) = rest.strip_prefix(">=") {
+        (Op::Ge, v)
+    } else if let Some(v) = rest.strip_prefix('<') {
+        (Op::Lt, v)
+    } else if let Some(v) = rest.strip_prefix('>') {
+        (Op::Gt, v)
+    } else {
+        (Op::Eq, &rest[1..])
+    };
+    Ok(Cond {
+        category: category_idx(categories, name),
+        op,
+        value: value.parse()?,
+    })
+}
+
+impl FromStr for System {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (workflows_s, parts_s) = s.split_once("\n\n").wrap_err("no parts")?;
+
+        let mut categories = vec![];
+        let mut workflows = HashMap::new();
+        for l in workflows_s.lines() {
+            let (name, rest) = l.split_once('{').wrap_err("invalid workflow")?;
+            let rest = rest.strip_suffix('}').wrap_err("invalid workflow")?;
+
+            let mut rules = vec![];
+            for s in rest.split(',') {
+                let rule = match s.split_once(':') {
+                    Some((cond, dest)) => Rule {
+                        cond: Some(parse_cond(&mut categories, cond)?),
+                        target: dest.into(),
+                    },
+                    None => Rule {
+                        cond: None,
+                        target: s.into(),
+                    },
+                };
+                rules.push(rule);
+            }
+            if workflows.insert(name.to_string(), rules).is_some() {
+                return Err(eyre!("duplicate workflow {}", name));
+            }
+        }
+
+        let mut values = vec![];
+        for l in parts_s.lines() {
+            let s = l
+                .strip_prefix('{')
+                .and_then(|s| s.strip_suffix('}'))
+                .wrap_err("invalid part")?;
+            let mut vals = vec![];
+            for s in s.split(',') {
+                let (name, val) = s.split_once('=').wrap_err("invalid part")?;
+                vals.push((category_idx(&mut categories, name), val.parse::<u64>()?));
+            }
+            values.push(vals);
+        }
+        let mut parts = vec![];
+        for (i, vals) in values.into_iter().enumerate() {
+            let mut part = vec![None; categories.len()];
+            for (c, v) in vals {
+                part[c] = Some(v);
+            }
+            let part = part
+                .into_iter()
+                .enumerate()
+                .map(|(c, v)| v.wrap_err_with(|| format!("part {} has no {}", i, categories[c])))
+                .collect::<Result<Vec<_>>>()?;
+            parts.push(part);
+        }
+
+        let system = System {
+            categories,
+            workflows,
+            parts,
+        };
+        system.validate()?;
+        Ok(system)
+    }
+}
+
+impl System {
+    /// Makes sure every workflow terminates on A or R
+    pub fn validate(&self) -> Result<()> {
+        for (name, rules) in self.workflows.iter() {
+            if rules.last().is_none_or(|r| r.cond.is_some()) {
+                return Err(eyre!("workflow {} has no fallback rule", name));
+            }
+            for rule in rules {
+                if let Target::Workflow(n) = &rule.target {
+                    if !self.workflows.contains_key(n) {
+                        return Err(eyre!("workflow {} sends to unknown workflow {}", name, n));
+                    }
+                }
+            }
+        }
+
+        let mut names: Vec<&String> = self.workflows.keys().collect();
+        names.sort();
+        let mut done = HashMap::new();
+        for name in names {
+            if let Some(cycle) = self.find_cycle(name, &mut done, &mut vec![]) {
+                return Err(eyre!("workflow cycle: {}", cycle.join(" -> ")));
+            }
+        }
+        Ok(())
+    }
+
+    fn find_cycle<'a>(
+        &'a self,
+        name: &'a str,
+        done: &mut HashMap<&'a str, bool>,
+        stack: &mut Vec<&'a str>,
+    ) -> Option<Vec<&'a str>> {
+        match done.get(name) {
+            Some(true) => return None,
+            Some(false) => {
+                let i = stack.iter().position(|n| *n == name).unwrap();
+                let mut cycle = stack[i..].to_vec();
+                cycle.push(name);
+                return Some(cycle);
+            }
+            None => {}
+        }
+
+        done.insert(name, false);
+        stack.push(name);
+        for rule in self.workflows[name].iter() {
+            if let Target::Workflow(n) = &rule.target {
+                if let Some(cycle) = self.find_cycle(n, done, stack) {
+                    return Some(cycle);
+                }
+            }
+        }
+        stack.pop();
+        done.insert(name, true);
+        None
+    }
+
+    pub fn cond_str(&self, cond: &Cond) -> String {
+        format!(
+            "{}{}{}",
+            self.categories[cond.category],
+            cond.op.symbol(),
+            cond.value
+        )
+    }
+
+    fn rules(&self, name: &str) -> Result<&[Rule]> {
+        self.workflows
+            .get(name)
+            .map(|r| r.as_slice())
+            .wrap_err_with(|| format!("unknown workflow {}", name))
+    }
+
+    pub fn run(&self, start: &str, part: &[u64]) -> Result<bool> {
+        let mut name = start;
+        loop {
+            let rule = self
+                .rules(name)?
+                .iter()
+                .find(|r| {
+                    r.cond
+                        .as_ref()
+                        .is_none_or(|c| c.range().contains(&part[c.category]))
+                })
+                .wrap_err_with(|| format!("no rule matching in {}", name))?;
+            match &rule.target {
+                Target::Accept => return Ok(true),
+                Target::Reject => return Ok(false),
+                Target::Workflow(n) => name = n,
+            }
+        }
+    }
+
+    pub fn accepted_boxes(&self, start: &str, bx: Hyperbox) -> Result<Vec<Hyperbox>> {
+        let mut res = vec![];
+        self.walk(start, bx, &mut |name, i, b| {
+            if self.workflows[name][i].target == Target::Accept {
+                res.push(b.clone());
+            }
+        })?;
+        Ok(res)
+    }
+
+    /// Sends `bx` through the workflows, calling `f` with every non-empty box a rule fires on
+    pub fn walk<'a>(
+        &'a self,
+        name: &'a str,
+        bx: Hyperbox,
+        f: &mut impl FnMut(&'a str, usize, &Hyperbox),
+    ) -> Result<()> {
+        let rules = self.rules(name)?;
+        let mut rest = vec![bx];
+        for (i, rule) in rules.iter().enumerate() {
+            let mut next = vec![];
+            for b in rest {
+                let (t, false_boxes) = match &rule.cond {
+                    Some(cond) => b.split(cond),
+                    None => (Some(b), vec![]),
+                };
+                next.extend(false_boxes);
+                let Some(t) = t else {
+                    continue;
+                };
+                f(name, i, &t);
+                if let Target::Workflow(n) = &rule.target {
+                    self.walk(n, t, f)?;
+                }
+            }
+            rest = next;
+            if rest.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn count_accepted(&self, start: &str, bx: Hyperbox) -> Result<u128> {
+        let mut sum: u128 = 0;
+        for b in self.accepted_boxes(start, bx)? {
+            sum = b
+                .volume()
+                .and_then(|v| sum.checked_add(v))
+                .wrap_err("accepted volume overflow")?;
+        }
+        Ok(sum)
+    }
+}
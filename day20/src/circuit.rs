@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
+use common::dot::Dot;
+
+/// Module reacting to pulses, new kinds of gates only need to implement this
+pub trait Gate {
+    fn kind(&self) -> &'static str;
+
+    /// Called for each module wired to this one before any pulse
+    fn connect(&mut self, _input: usize) {}
+
+    /// Handles a pulse coming from `from`, `None` for an external port, and
+    /// returns the level sent to every output, if any
+    fn receive(&mut self, from: Option<usize>, high: bool) -> Option<bool>;
+
+    /// Bits of memory, shown in traces
+    fn state(&self) -> Vec<bool> {
+        vec![]
+    }
+}
+
+/// Forwards every pulse
+#[derive(Debug, Default)]
+pub struct Broadcast;
+
+impl Gate for Broadcast {
+    fn kind(&self) -> &'static str {
+        "broadcast"
+    }
+
+    fn receive(&mut self, _from: Option<usize>, high: bool) -> Option<bool> {
+        Some(high)
+    }
+}
+
+/// Toggles on low pulses and sends its new state, ignores high pulses
+#[derive(Debug, Default)]
+pub struct FlipFlop {
+    pub on: bool,
+}
+
+impl Gate for FlipFlop {
+    fn kind(&self) -> &'static str {
+        "flip-flop"
+    }
+
+    fn receive(&mut self, _from: Option<usize>, high: bool) -> Option<bool> {
+        if high {
+            return None;
+        }
+        self.on = !self.on;
+        Some(self.on)
+    }
+
+    fn state(&self) -> Vec<bool> {
+        vec![self.on]
+    }
+}
+
+/// Remembers the last pulse of each input, sends a low pulse once they are
+/// all high
+#[derive(Debug, Default)]
+pub struct Conjunction {
+    pub memory: Vec<(usize, bool)>,
+}
+
+impl Gate for Conjunction {
+    fn kind(&self) -> &'static str {
+        "conjunction"
+    }
+
+    fn connect(&mut self, input: usize) {
+        self.memory.push((input, false));
+    }
+
+    fn receive(&mut self, from: Option<usize>, high: bool) -> Option<bool> {
+        if let Some(last) = self.memory.iter_mut().find(|(i, _)| Some(*i) == from) {
+            last.1 = high;
+        }
+        Some(!self.memory.iter().all(|(_, h)| *h))
+    }
+
+    fn state(&self) -> Vec<bool> {
+        self.memory.iter().map(|(_, h)| *h).collect()
+    }
+}
+
+/// Untyped module, only receives pulses
+#[derive(Debug, Default)]
+pub struct Sink;
+
+impl Gate for Sink {
+    fn kind(&self) -> &'static str {
+        "sink"
+    }
+
+    fn receive(&mut self, _from: Option<usize>, _high: bool) -> Option<bool> {
+        None
+    }
+}
+
+/// Gate and name of a module definition like `%a` or `broadcaster`
+fn gate(def: &str) -> (&str, Box<dyn Gate>) {
+    if let Some(name) = def.strip_prefix('%') {
+        (name, Box::<FlipFlop>::default())
+    } else if let Some(name) = def.strip_prefix('&') {
+        (name, Box::<Conjunction>::default())
+    } else if def == "broadcaster" {
+        (def, Box::new(Broadcast))
+    } else {
+        (def, Box::new(Sink))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal {
+    /// `None` for pulses injected from outside
+    pub from: Option<usize>,
+    pub to: usize,
+    pub high: bool,
+}
+
+/// Pulse delivered while tracing
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub press: usize,
+    /// Position of the pulse in the queue of its press
+    pub order: usize,
+    pub signal: Signal,
+    /// Level the receiver sent in response, if any
+    pub sent: Option<bool>,
+    /// State of the receiver once it handled the pulse
+    pub state: Vec<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trace {
+    /// State of each module when tracing started
+    pub initial: Vec<Vec<bool>>,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub received_low: usize,
+    pub received_high: usize,
+    pub sent_low: usize,
+    pub sent_high: usize,
+}
+
+pub struct Circuit {
+    pub names: Vec<String>,
+    pub gates: Vec<Box<dyn Gate>>,
+    /// Outputs of each module
+    pub cast: Vec<Vec<usize>>,
+    /// Inputs of each module
+    pub inputs: Vec<Vec<usize>>,
+    pub index: HashMap<String, usize>,
+    pub stats: Vec<Stats>,
+    /// Button presses so far
+    pub presses: usize,
+    pub trace: Option<Trace>,
+}
+
+impl FromStr for Circuit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut circuit = Circuit {
+            names: vec![],
+            gates: vec![],
+            cast: vec![],
+            inputs: vec![],
+            index: HashMap::new(),
+            stats: vec![],
+            presses: 0,
+            trace: None,
+        };
+        let mut outputs = vec![];
+        for l in s.lines() {
+            let (def, out) = l
+                .split_once(" -> ")
+                .wrap_err_with(|| format!("invalid module {:?}", l))?;
+            let (name, gate) = gate(def);
+            if circuit.index.contains_key(name) {
+                return Err(eyre!("module {} is defined twice", name));
+            }
+            circuit.add(name, gate);
+            outputs.push(out.split(", ").collect::<Vec<_>>());
+        }
+
+        for (mi, out) in outputs.into_iter().enumerate() {
+            for name in out {
+                let to = match circuit.index.get(name) {
+                    Some(to) => *to,
+                    None => circuit.add(name, Box::new(Sink)),
+                };
+                circuit.cast[mi].push(to);
+                circuit.inputs[to].push(mi);
+                circuit.gates[to].connect(mi);
+            }
+        }
+        Ok(circuit)
+    }
+}
+
+impl Circuit {
+    fn add(&mut self, name: &str, gate: Box<dyn Gate>) -> usize {
+        let mi = self.names.len();
+        self.names.push(name.to_string());
+        self.gates.push(gate);
+        self.cast.push(vec![]);
+        self.inputs.push(vec![]);
+        self.index.insert(name.to_string(), mi);
+        self.stats.push(Stats::default());
+        mi
+    }
+
+    pub fn id(&self, name: &str) -> Result<usize> {
+        self.index
+            .get(name)
+            .copied()
+            .wrap_err_with(|| format!("no module {}", name))
+    }
+
+    /// Records every pulse delivered from now on
+    pub fn record(&mut self) {
+        self.trace = Some(Trace {
+            initial: self.gates.iter().map(|g| g.state()).collect(),
+            events: vec![],
+        });
+    }
+
+    /// Sends `pulses` through the circuit until it settles or `stop` returns
+    /// true for a delivered pulse, returns whether it stopped early
+    pub fn inject(
+        &mut self,
+        pulses: impl IntoIterator<Item = Signal>,
+        mut stop: impl FnMut(&Signal) -> bool,
+    ) -> bool {
+        let mut queue: VecDeque<Signal> = pulses.into_iter().collect();
+        let mut order = 0;
+        while let Some(signal) = queue.pop_front() {
+            let stats = &mut self.stats[signal.to];
+            if signal.high {
+                stats.received_high += 1;
+            } else {
+                stats.received_low += 1;
+            }
+            if stop(&signal) {
+                return true;
+            }
+
+            let sent = self.gates[signal.to].receive(signal.from, signal.high);
+            if let Some(trace) = self.trace.as_mut() {
+                trace.events.push(Event {
+                    press: self.presses,
+                    order,
+                    signal,
+                    sent,
+                    state: self.gates[signal.to].state(),
+                });
+            }
+            order += 1;
+            let Some(high) = sent else {
+                continue;
+            };
+            let stats = &mut self.stats[signal.to];
+            if high {
+                stats.sent_high += self.cast[signal.to].len();
+            } else {
+                stats.sent_low += self.cast[signal.to].len();
+            }
+            for to in self.cast[signal.to].iter() {
+                queue.push_back(Signal {
+                    from: Some(signal.to),
+                    to: *to,
+                    high,
+                });
+            }
+        }
+        false
+    }
+
+    /// Pushes the button, sending a low pulse to the broadcaster
+    pub fn press(&mut self, stop: impl FnMut(&Signal) -> bool) -> Result<bool> {
+        let to = self.id("broadcaster")?;
+        self.presses += 1;
+        let button = Signal {
+            from: None,
+            to,
+            high: false,
+        };
+        Ok(self.inject([button], stop))
+    }
+
+    /// Presses the button until `stop` returns true, returns the press it
+    /// happened on
+    pub fn run_until(
+        &mut self,
+        max_presses: usize,
+        mut stop: impl FnMut(&Signal) -> bool,
+    ) -> Result<Option<usize>> {
+        while self.presses < max_presses {
+            if self.press(&mut stop)? {
+                return Ok(Some(self.presses));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Modules shaped by the kind of their gate
+    pub fn to_dot(&self) -> Dot {
+        let mut dot = Dot::new("circuit", true);
+        for (mi, name) in self.names.iter().enumerate() {
+            let (shape, color) = match self.gates[mi].kind() {
+                "broadcast" => ("doublecircle", "green"),
+                "flip-flop" => ("box", "lightblue"),
+                "conjunction" => ("diamond", "orange"),
+                _ => ("doubleoctagon", "red"),
+            };
+            dot.node(
+                name,
+                &[("shape", shape), ("style", "filled"), ("fillcolor", color)],
+            );
+        }
+        for (mi, cast) in self.cast.iter().enumerate() {
+            for to in cast.iter() {
+                dot.edge(&self.names[mi], &self.names[*to], &[]);
+            }
+        }
+        dot
+    }
+}
@@ -1,132 +1,10 @@
-use std::{
-    collections::{HashMap, VecDeque},
-    time::Instant,
-};
-
-use color_eyre::eyre::Result;
-
-#[derive(Debug, Clone)]
-struct Module {
-    name: String,
-    typ: ModuleType,
-    cast: Vec<usize>,
-}
-
-#[derive(Debug, Clone)]
-enum ModuleType {
-    Broadcast,
-    Flip,
-    Inv(Vec<usize>),
-    Empty,
-}
-
-fn parse(input: &str) -> (usize, Vec<Module>) {
-    let mut res = Vec::new();
-    let mut name_map = HashMap::new();
-    let mut args = Vec::new();
-    let mut bi = None;
-
-    for (i, l) in input.lines().enumerate() {
-        let (name, a) = l.split_once(" -> ").unwrap();
-        let a: Vec<&str> = a.split(", ").collect();
-        args.push(a.clone());
-        if name == "broadcaster" {
-            bi = Some(i);
-            let m = Module {
-                name: name.to_string(),
-                typ: ModuleType::Broadcast,
-                cast: Default::default(),
-            };
-            res.push(m);
-        } else if let Some(n) = name.strip_prefix("%") {
-            let m = Module {
-                name: n.to_string(),
-                typ: ModuleType::Flip,
-                cast: Default::default(),
-            };
-            name_map.insert(n, i);
-            res.push(m);
-        } else {
-            let n = &name[1..];
-            let m = Module {
-                name: n.to_string(),
-                typ: ModuleType::Inv(vec![]),
-                cast: Default::default(),
-            };
-            name_map.insert(n, i);
-            res.push(m);
-        }
-    }
-    for i in 0..res.len() {
-        for arg in &args[i] {
-            let v = if let Some(v) = name_map.get(arg) {
-                *v
-            } else {
-                let i = res.len();
-                res.push(Module {
-                    name: arg.to_string(),
-                    typ: ModuleType::Empty,
-                    cast: vec![],
-                });
-                i
-            };
-            if let ModuleType::Inv(ref mut inputs) = res[v].typ {
-                inputs.push(i);
-            }
-            res[i].cast.push(v);
-        }
-    }
+mod circuit;
+mod vcd;
 
-    (bi.unwrap(), res)
-}
+use std::time::Instant;
 
-fn step(
-    bi: usize,
-    modules: &[Module],
-    flip_state: &mut [bool],
-    inv_state: &mut [HashMap<usize, bool>],
-    lows: &mut usize,
-    highs: &mut usize,
-) {
-    let mut queue = VecDeque::new();
-    // we press the button
-    *lows += 1;
-    queue.push_back((bi, false));
-    while let Some((mi, mut high)) = queue.pop_front() {
-        let module = &modules[mi];
-        match module.typ {
-            ModuleType::Broadcast => {}
-            ModuleType::Flip => {
-                // high pulse, it is ignored and nothing happens
-                if high {
-                    continue;
-                }
-                let flipped = flip_state[mi];
-                high = !flipped;
-                flip_state[mi] = !flipped;
-            }
-            ModuleType::Inv(ref inputs) => {
-                let last_pulses = &mut inv_state[mi];
-                // high pulses for all inputs, send low pulse
-                high = !inputs.iter().all(|i| *last_pulses.get(i).unwrap_or(&false));
-            }
-            ModuleType::Empty => {}
-        }
-
-        for mii in &module.cast {
-            if high {
-                *highs += 1;
-            } else {
-                *lows += 1;
-            }
-
-            if let ModuleType::Inv(_) = modules[*mii].typ {
-                inv_state[*mii].insert(mi, high);
-            }
-            queue.push_back((*mii, high));
-        }
-    }
-}
+use circuit::{Circuit, Signal};
+use color_eyre::eyre::{eyre, ContextCompat, Result};
 
 fn gcd(a: usize, b: usize) -> usize {
     if b == 0 {
@@ -145,22 +23,10 @@ fn lcm_multi(numbers: &[usize]) -> usize {
 }
 
 fn part1(input: &str) -> Result<usize> {
-    let (bi, modules) = parse(input);
-    let mut lows = 0;
-    let mut highs = 0;
-    let mut flip_state = vec![false; modules.len()];
-    let mut inv_state = vec![HashMap::new(); modules.len()];
-
-    for _ in 0..1000 {
-        step(
-            bi,
-            &modules,
-            &mut flip_state,
-            &mut inv_state,
-            &mut lows,
-            &mut highs,
-        );
-    }
+    let mut circuit: Circuit = input.parse()?;
+    circuit.run_until(1000, |_| false)?;
+    let lows: usize = circuit.stats.iter().map(|s| s.received_low).sum();
+    let highs: usize = circuit.stats.iter().map(|s| s.received_high).sum();
     Ok(highs * lows)
 }
 
@@ -173,41 +39,120 @@ fn part2(input: &str) -> Result<usize> {
     // the 'rx' target receives a low pulse when all 4 binary counters are synchronized, i.e.
     // when they all receive a high pulse at the same time
     //
-    // this basically means that we need to first find out the binary number for the 4 counters and
-    // then compute the least common multiple of the 4 numbers
-
-    let (bi, modules) = parse(input);
-    let mut vals = vec![];
-    for mi in &modules[bi].cast {
-        let module = &modules[*mi];
-        let mut s = String::new();
-        let mut node = *mi;
-        loop {
-            let m = &modules[node];
-            if m.cast.len() == 2 || s.len() == 11 {
-                s.push('1');
-            } else {
-                s.push('0');
-            }
-            let mut found = false;
-            for n in &m.cast {
-                let m = &modules[*n];
-                if let ModuleType::Flip = m.typ {
-                    found = true;
-                    node = *n;
-                }
-            }
-            if !found {
-                break;
+    // this basically means that we need to first find out the period of the 4 counters, i.e.
+    // the first press sending a high pulse to the conjunction in front of 'rx', and then
+    // compute the least common multiple of the 4 numbers
+    //
+    // `cargo run -- --vcd trace.vcd 5000` dumps the pulses to watch the counters in a waveform viewer
+
+    let mut circuit: Circuit = input.parse()?;
+    let rx = circuit.id("rx")?;
+    let [feeder] = circuit.inputs[rx][..] else {
+        return Err(eyre!("rx is not fed by a single conjunction"));
+    };
+    let counters = circuit.inputs[feeder].clone();
+    let mut periods = vec![None; counters.len()];
+    let mut presses = 0;
+    circuit.run_until(1 << 20, |signal| {
+        // the closure does not see press numbers, counting button pulses instead
+        if signal.from.is_none() {
+            presses += 1;
+        }
+        if signal.to == feeder && signal.high {
+            let ci = counters.iter().position(|c| Some(*c) == signal.from);
+            if let Some(ci) = ci {
+                periods[ci].get_or_insert(presses);
             }
         }
-        // last bit is always 1
-        let s = "1".to_string() + &s.chars().rev().collect::<String>()[1..];
-        let v = usize::from_str_radix(&s, 2).unwrap();
-        vals.push(v);
+        periods.iter().all(|p| p.is_some())
+    })?;
+    let periods: Option<Vec<usize>> = periods.into_iter().collect();
+    Ok(lcm_multi(&periods.wrap_err("a counter never fired")?))
+}
+
+fn parse_level(level: &str) -> Result<bool> {
+    match level {
+        "low" => Ok(false),
+        "high" => Ok(true),
+        _ => Err(eyre!("level is low or high")),
+    }
+}
+
+/// Pulses received and sent by each module
+fn print_stats(circuit: &Circuit) {
+    println!("module\tkind\tlow in\thigh in\tlow out\thigh out");
+    for (mi, s) in circuit.stats.iter().enumerate() {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            circuit.names[mi],
+            circuit.gates[mi].kind(),
+            s.received_low,
+            s.received_high,
+            s.sent_low,
+            s.sent_high
+        );
+    }
+}
+
+/// Presses the button until `module` receives a pulse of the given level
+fn until(input: &str, module: &str, level: &str, max_presses: usize) -> Result<()> {
+    let mut circuit: Circuit = input.parse()?;
+    let mi = circuit.id(module)?;
+    let high = parse_level(level)?;
+    match circuit.run_until(max_presses, |s| s.to == mi && s.high == high)? {
+        Some(n) => println!("[*] {} receives a {} pulse on press {}", module, level, n),
+        None => println!(
+            "[*] no {} pulse to {} in {} presses",
+            level, module, max_presses
+        ),
+    }
+    Ok(())
+}
+
+fn query(input: &str, args: &[String]) -> Result<()> {
+    match args {
+        [cmd, rest @ ..] if cmd == "--stats" && rest.len() <= 1 => {
+            let presses = rest.first().map_or(Ok(1000), |n| n.parse())?;
+            let mut circuit: Circuit = input.parse()?;
+            circuit.run_until(presses, |_| false)?;
+            print_stats(&circuit);
+            Ok(())
+        }
+        [cmd, module, level] if cmd == "--inject" => {
+            // single pulse from outside, without pressing the button
+            let mut circuit: Circuit = input.parse()?;
+            let signal = Signal {
+                from: None,
+                to: circuit.id(module)?,
+                high: parse_level(level)?,
+            };
+            circuit.inject([signal], |_| false);
+            print_stats(&circuit);
+            Ok(())
+        }
+        [cmd, module, level, rest @ ..] if cmd == "--until" && rest.len() <= 1 => {
+            let max = rest.first().map_or(Ok(1 << 20), |n| n.parse())?;
+            until(input, module, level, max)
+        }
+        [cmd, file, rest @ ..] if cmd == "--vcd" && rest.len() <= 1 => {
+            let presses = rest.first().map_or(Ok(1000), |n| n.parse())?;
+            let mut circuit: Circuit = input.parse()?;
+            circuit.record();
+            circuit.run_until(presses, |_| false)?;
+            let trace = circuit.trace.as_ref().wrap_err("no trace")?;
+            std::fs::write(file, vcd::export(&circuit, trace))?;
+            println!("[*] {} pulses written to {}", trace.events.len(), file);
+            Ok(())
+        }
+        [cmd, file] if cmd == "--dot" => {
+            let circuit: Circuit = input.parse()?;
+            std::fs::write(file, circuit.to_dot().to_string())?;
+            Ok(())
+        }
+        _ => Err(eyre!(
+            "usage: --stats [presses] | --inject <module> <low|high> | --until <module> <low|high> [max presses] | --vcd <file> [presses] | --dot <file>"
+        )),
     }
-    let lcm = lcm_multi(&vals);
-    Ok(lcm)
 }
 
 fn main() -> Result<()> {
@@ -215,6 +160,11 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return query(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
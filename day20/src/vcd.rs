@@ -0,0 +1,84 @@
+use std::fmt::Write;
+
+use crate::circuit::{Circuit, Trace};
+
+/// Short printable identifier of the `i`-th variable
+fn ident(mut i: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (i % 94) as u8) as char);
+        i /= 94;
+        if i == 0 {
+            return id;
+        }
+        i -= 1;
+    }
+}
+
+fn bits(state: &[bool]) -> String {
+    state.iter().map(|b| if *b { '1' } else { '0' }).collect()
+}
+
+/// Value Change Dump of a trace, one time step per delivered pulse.
+/// Each module shows the level it last sent and its memory bits.
+pub fn export(circuit: &Circuit, trace: &Trace) -> String {
+    let mut out = String::new();
+    let mut next = 0;
+    let mut var = |out: &mut String, kind: &str, width: usize, name: &str| {
+        let id = ident(next);
+        next += 1;
+        writeln!(out, "$var {} {} {} {} $end", kind, width, id, name).unwrap();
+        id
+    };
+
+    out.push_str("$timescale 1ns $end\n$scope module circuit $end\n");
+    let press = var(&mut out, "integer", 32, "press");
+    let mut outputs = vec![None; circuit.names.len()];
+    let mut states = vec![None; circuit.names.len()];
+    for (mi, name) in circuit.names.iter().enumerate() {
+        if !circuit.cast[mi].is_empty() {
+            outputs[mi] = Some(var(&mut out, "wire", 1, name));
+        }
+        let width = trace.initial[mi].len();
+        if width > 0 {
+            states[mi] = Some(var(&mut out, "reg", width, &format!("{}_state", name)));
+        }
+    }
+    out.push_str("$upscope $end\n$enddefinitions $end\n");
+
+    out.push_str("#0\n$dumpvars\nb0 ");
+    out.push_str(&press);
+    out.push('\n');
+    for id in outputs.iter().flatten() {
+        writeln!(out, "0{}", id).unwrap();
+    }
+    for (mi, id) in states.iter().enumerate() {
+        if let Some(id) = id {
+            writeln!(out, "b{} {}", bits(&trace.initial[mi]), id).unwrap();
+        }
+    }
+    out.push_str("$end\n");
+
+    let mut last_state = trace.initial.clone();
+    let mut last_sent = vec![false; circuit.names.len()];
+    for (t, event) in trace.events.iter().enumerate() {
+        writeln!(out, "#{}", t + 1).unwrap();
+        if event.order == 0 {
+            writeln!(out, "b{:b} {}", event.press, press).unwrap();
+        }
+        let mi = event.signal.to;
+        if let (Some(high), Some(id)) = (event.sent, &outputs[mi]) {
+            if high != last_sent[mi] {
+                writeln!(out, "{}{}", high as u8, id).unwrap();
+                last_sent[mi] = high;
+            }
+        }
+        if let Some(id) = &states[mi] {
+            if event.state != last_state[mi] {
+                writeln!(out, "b{} {}", bits(&event.state), id).unwrap();
+                last_state[mi].clone_from(&event.state);
+            }
+        }
+    }
+    out
+}
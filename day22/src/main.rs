@@ -1,9 +1,10 @@
 use std::{
-    ops::{Add, Index, IndexMut, Sub},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
     time::Instant,
 };
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, ContextCompat, Result};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
@@ -18,201 +19,228 @@ impl Point {
     }
 }
 
-impl PartialOrd for Point {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+/// Cuboid between `min` and `max`, both included
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Brick {
+    /// Line of the brick in the input
+    pub id: usize,
+    pub min: Point,
+    pub max: Point,
 }
 
-impl Ord for Point {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.z
-            .cmp(&other.z)
-            .then(self.y.cmp(&other.y))
-            .then(self.x.cmp(&other.x))
-    }
+/// Settled bricks, sorted so a brick always comes after the ones holding it
+#[derive(Debug, Clone)]
+struct Stack {
+    pub bricks: Vec<Brick>,
+    /// Bricks resting on each brick
+    pub supports: Vec<Vec<usize>>,
+    /// Bricks each brick rests on, empty when on the ground
+    pub supported_by: Vec<Vec<usize>>,
 }
 
-impl Add for Point {
-    type Output = Self;
-    fn add(self, other: Self) -> Self {
-        Point {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
+fn parse(input: &str) -> Vec<Brick> {
+    let mut bricks = Vec::new();
+    for (id, line) in input.lines().enumerate() {
+        let mut points = Vec::new();
+        for val in line.split('~') {
+            let mut v = val.split(',');
+            let x = v.next().unwrap().parse().unwrap();
+            let y = v.next().unwrap().parse().unwrap();
+            let z = v.next().unwrap().parse().unwrap();
+            points.push(Point::new(x, y, z));
         }
+        let (a, b) = (points[0], points[1]);
+        bricks.push(Brick {
+            id,
+            min: Point::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        });
     }
+    bricks
 }
 
-impl Sub for Point {
-    type Output = Self;
-    fn sub(self, other: Self) -> Self {
-        Point {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
-        }
-    }
-}
+fn fall(mut bricks: Vec<Brick>) -> Stack {
+    bricks.sort_by_key(|b| b.min.z);
+    let max_x = bricks.iter().map(|b| b.max.x).max().unwrap_or(0) as usize + 1;
+    let max_y = bricks.iter().map(|b| b.max.y).max().unwrap_or(0) as usize + 1;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-struct Brick(Vec<Point>);
+    // highest z and brick for each (x, y) column
+    let mut heights: Vec<(i32, Option<usize>)> = vec![(0, None); max_x * max_y];
+    let mut supports = vec![vec![]; bricks.len()];
+    let mut supported_by = vec![vec![]; bricks.len()];
+    for bi in 0..bricks.len() {
+        let b = &bricks[bi];
+        let cells: Vec<usize> = (b.min.y..=b.max.y)
+            .flat_map(|y| (b.min.x..=b.max.x).map(move |x| y as usize * max_x + x as usize))
+            .collect();
 
-#[derive(Debug, Clone)]
-struct Grid {
-    pub max_x: i32,
-    pub max_y: i32,
-    pub max_z: i32,
-    pub bytes: Vec<u8>,
-}
+        let top = cells.iter().map(|c| heights[*c].0).max().unwrap();
+        for c in cells.iter() {
+            if let (z, Some(below)) = heights[*c] {
+                if z == top && !supported_by[bi].contains(&below) {
+                    supported_by[bi].push(below);
+                    supports[below].push(bi);
+                }
+            }
+        }
 
-impl IndexMut<Point> for Grid {
-    fn index_mut(&mut self, index: Point) -> &mut u8 {
-        &mut self.bytes
-            [(index.z * self.max_y * self.max_x + index.y * self.max_x + index.x) as usize]
+        let b = &mut bricks[bi];
+        let drop = b.min.z - (top + 1);
+        b.min.z -= drop;
+        b.max.z -= drop;
+        for c in cells {
+            heights[c] = (b.max.z, Some(bi));
+        }
     }
-}
 
-impl Index<Point> for Grid {
-    type Output = u8;
-
-    fn index(&self, index: Point) -> &Self::Output {
-        &self.bytes[(index.z * self.max_y * self.max_x + index.y * self.max_x + index.x) as usize]
+    Stack {
+        bricks,
+        supports,
+        supported_by,
     }
 }
 
-fn parse(input: &str) -> (Vec<Brick>, Grid) {
-    let mut bricks = Vec::new();
-    let mut max_x = 0;
-    let mut max_y = 0;
-    let mut max_z = 0;
-    for line in input.lines() {
-        let s = line.split("~");
-        let mut points = Vec::new();
-        for val in s {
-            let mut v = val.split(",");
-            let x = v.next().unwrap().parse().unwrap();
-            let y = v.next().unwrap().parse().unwrap();
-            let z = v.next().unwrap().parse().unwrap();
-            let point = Point::new(x, y, z);
-            points.push(point);
-        }
-        max_x = max_x.max(points.iter().map(|p| p.x).max().unwrap());
-        max_y = max_y.max(points.iter().map(|p| p.y).max().unwrap());
-        max_z = max_z.max(points.iter().map(|p| p.z).max().unwrap());
-
-        let mut line = Vec::new();
-        let mut p = points[0];
-        let mut dir = points[1] - points[0];
-        if dir.x != 0 {
-            dir.x /= dir.x;
-        }
-        if dir.y != 0 {
-            dir.y /= dir.y;
-        }
-        if dir.z != 0 {
-            dir.z /= dir.z;
+impl Stack {
+    /// Immediate dominator of each brick, `None` standing for the ground:
+    /// the lowest brick whose removal makes it fall
+    fn dominators(&self) -> Vec<Option<usize>> {
+        let mut idom: Vec<Option<usize>> = vec![None; self.bricks.len()];
+        let mut depth = vec![0; self.bricks.len()];
+        let depth_of = |depth: &[usize], b: Option<usize>| b.map_or(0, |b| depth[b] + 1);
+
+        // bricks are already in topological order
+        for bi in 0..self.bricks.len() {
+            let mut below = self.supported_by[bi].iter().map(|b| Some(*b));
+            let mut dom = below.next().flatten();
+            for mut other in below {
+                while dom != other {
+                    if depth_of(&depth, dom) > depth_of(&depth, other) {
+                        dom = idom[dom.unwrap()];
+                    } else {
+                        other = idom[other.unwrap()];
+                    }
+                }
+            }
+            idom[bi] = dom;
+            depth[bi] = depth_of(&depth, dom);
         }
-        while p != points[1] {
-            line.push(p);
-            p = p + dir;
+        idom
+    }
+
+    /// Number of other bricks falling when each brick is removed
+    fn chain_reactions(&self) -> Vec<usize> {
+        let idom = self.dominators();
+        let mut sizes = vec![1; self.bricks.len()];
+        for bi in (0..self.bricks.len()).rev() {
+            if let Some(d) = idom[bi] {
+                sizes[d] += sizes[bi];
+            }
         }
-        line.push(points[1]);
-        bricks.push(Brick(line));
+        sizes.into_iter().map(|s| s - 1).collect()
     }
 
-    max_x += 1;
-    max_y += 1;
-    max_z += 1;
-    let bytes = vec![0u8; (max_x * max_y * max_z) as usize];
-    let mut plane = Grid {
-        max_x,
-        max_y,
-        max_z,
-        bytes,
-    };
-    for brick in &bricks {
-        for p in &brick.0 {
-            plane[*p] = 1;
+    /// Bricks falling once all of `removed` are gone
+    fn falling(&self, removed: &[usize]) -> Vec<usize> {
+        let mut gone = vec![false; self.bricks.len()];
+        for bi in removed {
+            gone[*bi] = true;
+        }
+        let mut res = vec![];
+        for bi in 0..self.bricks.len() {
+            let below = &self.supported_by[bi];
+            if !gone[bi] && !below.is_empty() && below.iter().all(|b| gone[*b]) {
+                gone[bi] = true;
+                res.push(bi);
+            }
         }
+        res
     }
 
-    (bricks, plane)
-}
+    /// Brick whose removal makes the most other bricks fall
+    fn most_critical(&self) -> Option<(usize, usize)> {
+        self.chain_reactions()
+            .into_iter()
+            .enumerate()
+            .max_by_key(|(_, n)| *n)
+    }
 
-fn fall(bricks: &mut [Brick], grid: &mut Grid) -> usize {
-    let mut new_fall = 0;
-    let fp = Point::new(0, 0, 1);
-    for bi in 0..bricks.len() {
-        let mut can_fall = true;
-        let mut did_fall = false;
-        while can_fall {
-            let mut new_brick = bricks[bi].clone();
-            for pi in 0..bricks[bi].0.len() {
-                let np = bricks[bi].0[pi] - fp;
-                if !(bricks[bi].0.contains(&np)) && (grid[np] == 1 || np.z == 0) {
-                    can_fall = false;
-                    break;
+    /// Removes the highest brick with nothing left on top of it, until the
+    /// stack is gone, so no brick ever falls
+    fn dismantle_order(&self) -> Vec<usize> {
+        let mut on_top: Vec<usize> = self.supports.iter().map(|s| s.len()).collect();
+        let mut queue: BinaryHeap<(i32, Reverse<usize>)> = (0..self.bricks.len())
+            .filter(|bi| on_top[*bi] == 0)
+            .map(|bi| (self.bricks[bi].max.z, Reverse(bi)))
+            .collect();
+        let mut order = vec![];
+        while let Some((_, Reverse(bi))) = queue.pop() {
+            order.push(bi);
+            for b in self.supported_by[bi].iter() {
+                on_top[*b] -= 1;
+                if on_top[*b] == 0 {
+                    queue.push((self.bricks[*b].max.z, Reverse(*b)));
                 }
-                new_brick.0[pi] = np;
             }
-            if can_fall {
-                did_fall = true;
-                for p in &bricks[bi].0 {
-                    grid[*p] = 0;
-                }
-                bricks[bi] = new_brick;
-                for p in &bricks[bi].0 {
-                    grid[*p] = 1;
-                }
-            }
-        }
-        if did_fall {
-            new_fall += 1;
         }
+        order
     }
-    new_fall
 }
 
 fn part1(input: &str) -> Result<usize> {
-    let (mut bricks, mut grid) = parse(input);
-    bricks.sort();
-    // all bricks fall
-    fall(&mut bricks, &mut grid);
-
-    // check for each brick if it can be destroyed
-    let mut total = 0;
-    for bi in 0..bricks.len() {
-        let mut bricks = bricks.clone();
-        let mut grid = grid.clone();
-        for p in bricks.remove(bi).0 {
-            grid[p] = 0;
-        }
-        let r = fall(&mut bricks, &mut grid);
-        if r == 0 {
-            total += 1;
-        }
-    }
+    let stack = fall(parse(input));
+    let total = stack
+        .supports
+        .iter()
+        .filter(|above| above.iter().all(|a| stack.supported_by[*a].len() > 1))
+        .count();
     Ok(total)
 }
 
 fn part2(input: &str) -> Result<usize> {
-    let (mut bricks, mut grid) = parse(input);
-    bricks.sort();
-    // all bricks fall
-    fall(&mut bricks, &mut grid);
+    let stack = fall(parse(input));
+    Ok(stack.chain_reactions().iter().sum())
+}
 
-    let mut total = 0;
-    for bi in 0..bricks.len() {
-        let mut bricks = bricks.clone();
-        let mut grid = grid.clone();
-        for p in bricks.remove(bi).0 {
-            grid[p] = 0;
+fn query(input: &str, args: &[String]) -> Result<()> {
+    let stack = fall(parse(input));
+    let by_id: HashMap<usize, usize> = stack
+        .bricks
+        .iter()
+        .enumerate()
+        .map(|(bi, b)| (b.id, bi))
+        .collect();
+    let ids = |bricks: &[usize]| {
+        bricks
+            .iter()
+            .map(|bi| stack.bricks[*bi].id.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    match args {
+        [cmd, removed] if cmd == "--remove" => {
+            let mut bricks = vec![];
+            for id in removed.split(',') {
+                let id: usize = id.parse()?;
+                bricks.push(
+                    *by_id
+                        .get(&id)
+                        .wrap_err_with(|| format!("no brick {}", id))?,
+                );
+            }
+            let falling = stack.falling(&bricks);
+            println!("[*] {} bricks fall: {}", falling.len(), ids(&falling));
+        }
+        [cmd] if cmd == "--critical" => {
+            let (bi, n) = stack.most_critical().wrap_err("no bricks")?;
+            println!("[*] brick {} makes {} bricks fall", stack.bricks[bi].id, n);
         }
-        let r = fall(&mut bricks, &mut grid);
-        total += r;
+        [cmd] if cmd == "--dismantle" => {
+            println!("[*] {}", ids(&stack.dismantle_order()));
+        }
+        _ => return Err(eyre!("usage: --remove <id,...> | --critical | --dismantle")),
     }
-    Ok(total)
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -220,6 +248,12 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    // bricks are identified by their input line, e.g. `cargo run -- --remove 3,17`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return query(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
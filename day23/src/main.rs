@@ -2,112 +2,413 @@ use aoc_utils::{
     grid::Grid,
     point::{Point, ORTHO},
 };
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, ContextCompat, Result};
+use common::dot::Dot;
 use gxhash::{HashMap, HashMapExt, HashSet, HashSetExt};
-use std::{
-    collections::VecDeque,
-    time::Instant,
-};
+use rayon::prelude::*;
+use std::{cmp::Reverse, time::Instant};
 
 struct GridWrap {
     start: Point,
     end: Point,
-    edges: HashMap<Point, HashSet<(Point, usize)>>,
+    edges: HashMap<Point, Vec<(Point, usize)>>,
+}
+
+fn open_neighbours(grid: &Grid<u8>, p: Point) -> Vec<Point> {
+    ORTHO
+        .iter()
+        .map(|dir| p + *dir)
+        .filter(|np| grid.contains(*np) && grid[*np] != b'#')
+        .collect()
 }
 
-fn prune_graph(grid: Grid<u8>, p1: bool) -> GridWrap {
+/// Whether stepping onto `to` goes the way of its slope, if any
+fn downhill(grid: &Grid<u8>, from: Point, to: Point) -> bool {
+    let cp = to - from;
+    match grid[to] {
+        b'^' => cp == Point::new(0, -1),
+        b'<' => cp == Point::new(-1, 0),
+        b'>' => cp == Point::new(1, 0),
+        b'v' => cp == Point::new(0, 1),
+        _ => true,
+    }
+}
+
+/// Junctions are the cells with at least 3 open neighbours, slopes or not,
+/// plus both ends. Each corridor leaving a junction gives one edge.
+fn prune_graph(grid: &Grid<u8>, p1: bool, start: Point, end: Point) -> GridWrap {
+    let mut nodes = HashSet::new();
+    nodes.insert(start);
+    nodes.insert(end);
+    for y in 0..grid.height as i32 {
+        for x in 0..grid.width as i32 {
+            let p = Point::new(x, y);
+            if grid[p] != b'#' && open_neighbours(grid, p).len() >= 3 {
+                nodes.insert(p);
+            }
+        }
+    }
+
+    let mut edges: HashMap<Point, Vec<(Point, usize)>> = HashMap::new();
+    for node in nodes.iter() {
+        // nothing leaves the end, paths stop there
+        if *node == end {
+            continue;
+        }
+        'corridor: for first in open_neighbours(grid, *node) {
+            let (mut prev, mut p, mut len) = (*node, first, 1);
+            loop {
+                if p1 && !downhill(grid, prev, p) {
+                    continue 'corridor;
+                }
+                if nodes.contains(&p) {
+                    break;
+                }
+                let Some(next) = open_neighbours(grid, p).into_iter().find(|np| *np != prev) else {
+                    continue 'corridor;
+                };
+                (prev, p, len) = (p, next, len + 1);
+            }
+            if p != *node {
+                edges.entry(*node).or_default().push((p, len));
+            }
+        }
+    }
+    GridWrap { start, end, edges }
+}
+
+fn default_ends(grid: &Grid<u8>) -> (Point, Point) {
     let start = Point::new(1, 0);
     let end = Point::new((grid.width - 2) as i32, (grid.height - 1) as i32);
-    let mut edges: HashMap<Point, HashSet<(Point, usize)>> = HashMap::new();
-    let mut seen = HashSet::new();
-    let mut q = VecDeque::new();
-    let mut path = HashSet::new();
-    path.insert(start);
-    q.push_front((start, start, path));
-
-    while let Some((p, mut parent, mut path)) = q.pop_front() {
-        let mut neighs = Vec::new();
-        for dir in ORTHO {
-            let np = p + dir;
-            if path.contains(&np) {
-                continue;
+    (start, end)
+}
+
+fn parse(input: &str, p1: bool) -> GridWrap {
+    let grid = Grid::parse(input);
+    let (start, end) = default_ends(&grid);
+    prune_graph(&grid, p1, start, end)
+}
+
+fn parse_between(input: &str, p1: bool, start: Point, end: Point) -> Result<GridWrap> {
+    let grid = Grid::parse(input);
+    for p in [start, end] {
+        if !grid.contains(p) || grid[p] == b'#' {
+            return Err(eyre!("({},{}) is not on a trail", p.x, p.y));
+        }
+    }
+    Ok(prune_graph(&grid, p1, start, end))
+}
+
+/// Junction graph with dense ids, so a path fits in a u64 mask
+struct Graph {
+    junctions: Vec<Point>,
+    adj: Vec<Vec<(usize, usize)>>,
+    start: usize,
+    end: usize,
+    /// Only junction leading to the end, with the cost of that last edge
+    gate: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    length: usize,
+    junctions: Vec<Point>,
+}
+
+#[derive(Debug, Clone)]
+struct Stats {
+    paths: u64,
+    shortest: Option<Route>,
+    longest: Vec<Route>,
+}
+
+/// Current node, visited mask, length so far and path
+type State = (usize, u64, usize, Vec<usize>);
+/// Path count, shortest and longest paths as (length, junction ids)
+type StatsAcc = (u64, Option<(usize, Vec<usize>)>, Vec<(usize, Vec<usize>)>);
+
+fn junction_id(ids: &mut HashMap<Point, usize>, junctions: &mut Vec<Point>, p: Point) -> usize {
+    *ids.entry(p).or_insert_with(|| {
+        junctions.push(p);
+        junctions.len() - 1
+    })
+}
+
+impl GridWrap {
+    fn dense(&self) -> Result<Graph> {
+        let mut ids = HashMap::new();
+        let mut junctions = Vec::new();
+        let start = junction_id(&mut ids, &mut junctions, self.start);
+        let end = junction_id(&mut ids, &mut junctions, self.end);
+
+        let mut edges = Vec::new();
+        for (p, es) in self.edges.iter() {
+            let a = junction_id(&mut ids, &mut junctions, *p);
+            for (np, cost) in es.iter() {
+                let b = junction_id(&mut ids, &mut junctions, *np);
+                edges.push((a, b, *cost));
+            }
+        }
+        if junctions.len() > 64 {
+            return Err(eyre!("{} junctions don't fit in a u64", junctions.len()));
+        }
+
+        let mut adj = vec![Vec::new(); junctions.len()];
+        for (a, b, cost) in edges {
+            adj[a].push((b, cost));
+        }
+        for l in adj.iter_mut() {
+            l.sort();
+        }
+
+        let into_end: Vec<(usize, usize)> = (0..adj.len())
+            .flat_map(|a| {
+                adj[a]
+                    .iter()
+                    .filter(|(b, _)| *b == end)
+                    .map(move |(_, c)| (a, *c))
+            })
+            .collect();
+        let gate = (into_end.len() == 1).then(|| into_end[0]);
+
+        Ok(Graph {
+            junctions,
+            adj,
+            start,
+            end,
+            gate,
+        })
+    }
+}
+
+impl Graph {
+    fn next_steps(&self, node: usize, visited: u64) -> Vec<(usize, usize)> {
+        // leaving the gate for anything else than the end can't reach the end anymore
+        if let Some((gate, cost)) = self.gate {
+            if node == gate {
+                return vec![(self.end, cost)];
             }
-            if grid.contains(np) {
-                if grid[np] == b'#' {
+        }
+        self.adj[node]
+            .iter()
+            .filter(|(n, _)| visited & (1 << n) == 0)
+            .copied()
+            .collect()
+    }
+
+    fn dfs<T>(
+        &self,
+        node: usize,
+        visited: u64,
+        length: usize,
+        path: &mut Vec<usize>,
+        acc: &mut T,
+        f: &impl Fn(&mut T, usize, &[usize]),
+    ) {
+        if node == self.end {
+            f(acc, length, path);
+            return;
+        }
+        for (next, cost) in self.next_steps(node, visited) {
+            path.push(next);
+            self.dfs(next, visited | (1 << next), length + cost, path, acc, f);
+            path.pop();
+        }
+    }
+
+    /// Folds every simple path from start to end with `f`, the first
+    /// branching levels are expanded so each prefix gets its own thread
+    fn fold_paths<T: Send>(
+        &self,
+        init: impl Fn() -> T + Sync + Send,
+        f: impl Fn(&mut T, usize, &[usize]) + Sync + Send,
+        merge: impl Fn(T, T) -> T + Sync + Send,
+    ) -> T {
+        let mut frontier: Vec<State> = vec![(self.start, 1 << self.start, 0, vec![self.start])];
+        while frontier.len() < 64 {
+            let mut next_frontier = Vec::new();
+            let mut expanded = false;
+            for (node, visited, length, path) in frontier {
+                if node == self.end {
+                    next_frontier.push((node, visited, length, path));
                     continue;
                 }
-                if p1 && grid[np] != b'.' {
-                    let cp = np - p;
-                    let valid = match grid[np] {
-                        b'^' => cp == Point::new(0, -1),
-                        b'<' => cp == Point::new(-1, 0),
-                        b'>' => cp == Point::new(1, 0),
-                        b'v' => cp == Point::new(0, 1),
-                        _ => unreachable!(),
-                    };
-                    if !valid {
-                        continue;
-                    }
+                for (next, cost) in self.next_steps(node, visited) {
+                    let mut path = path.clone();
+                    path.push(next);
+                    next_frontier.push((next, visited | (1 << next), length + cost, path));
+                    expanded = true;
                 }
-                neighs.push(np);
+            }
+            frontier = next_frontier;
+            if !expanded {
+                break;
             }
         }
-        if p == end {
-            edges.entry(parent).or_default().insert((p, path.len() - 1));
-        }
-        if neighs.len() >= 2 {
-            edges.entry(parent).or_default().insert((p, path.len() - 1));
-            parent = p;
-            path.clear();
-            path.insert(p);
+
+        frontier
+            .into_par_iter()
+            .map(|(node, visited, length, mut path)| {
+                let mut acc = init();
+                self.dfs(node, visited, length, &mut path, &mut acc, &f);
+                acc
+            })
+            .reduce(&init, &merge)
+    }
+
+    fn route(&self, length: usize, path: &[usize]) -> Route {
+        Route {
+            length,
+            junctions: path.iter().map(|j| self.junctions[*j]).collect(),
         }
-        for neigh in neighs {
-            let mut path = path.clone();
-            path.insert(neigh);
-            if seen.insert((neigh, parent)) {
-                q.push_front((neigh, parent, path));
-            }
+    }
+
+    fn longest(&self) -> Option<Route> {
+        let best = self.fold_paths(
+            || None,
+            |best: &mut Option<(usize, Vec<usize>)>, length, path| {
+                if best.as_ref().is_none_or(|(l, _)| length > *l) {
+                    *best = Some((length, path.to_vec()));
+                }
+            },
+            |a, b| a.into_iter().chain(b).max_by_key(|(l, _)| *l),
+        );
+        best.map(|(length, path)| self.route(length, &path))
+    }
+
+    /// Number of simple paths, shortest one and the `k` longest ones
+    fn stats(&self, k: usize) -> Stats {
+        let (count, shortest, mut longest) = self.fold_paths(
+            || (0, None, Vec::new()),
+            |(count, shortest, longest): &mut StatsAcc, length, path| {
+                *count += 1;
+                if shortest.as_ref().is_none_or(|(l, _)| length < *l) {
+                    *shortest = Some((length, path.to_vec()));
+                }
+                if longest.len() < k || longest.last().is_some_and(|(l, _)| length > *l) {
+                    let i = longest.partition_point(|(l, _)| *l >= length);
+                    longest.insert(i, (length, path.to_vec()));
+                    longest.truncate(k);
+                }
+            },
+            |a, b| {
+                let shortest = a.1.into_iter().chain(b.1).min_by_key(|(l, _)| *l);
+                let mut longest = a.2;
+                longest.extend(b.2);
+                longest.sort_by_key(|(l, _)| Reverse(*l));
+                longest.truncate(k);
+                (a.0 + b.0, shortest, longest)
+            },
+        );
+        longest.truncate(k);
+        Stats {
+            paths: count,
+            shortest: shortest.map(|(length, path)| self.route(length, &path)),
+            longest: longest
+                .into_iter()
+                .map(|(length, path)| self.route(length, &path))
+                .collect(),
         }
     }
-    GridWrap { start, end, edges }
 }
 
-fn parse(input: &str, p1: bool) -> GridWrap {
-    let grid = Grid::parse(input);
-    prune_graph(grid, p1)
-}
-
-fn run(wrap: &GridWrap) -> usize {
-    let mut q = VecDeque::new();
-    let mut path = HashSet::new();
-    let mut best = 0;
-    path.insert(wrap.start);
-    q.push_back((wrap.start, path, 0));
-    while let Some((p, path, cur_cost)) = q.pop_front() {
-        if p == wrap.end {
-            best = best.max(cur_cost);
-            continue;
+impl Graph {
+    /// Junctions with the trail lengths between them, undirected edges are
+    /// written once
+    fn to_dot(&self, directed: bool) -> Dot {
+        let name = |j: usize| format!("{},{}", self.junctions[j].x, self.junctions[j].y);
+        let mut dot = Dot::new("junctions", directed);
+        dot.default_node(&[("shape", "circle")]);
+        dot.node(
+            &name(self.start),
+            &[("shape", "doublecircle"), ("color", "green")],
+        );
+        dot.node(
+            &name(self.end),
+            &[("shape", "doublecircle"), ("color", "red")],
+        );
+        if let Some((gate, _)) = self.gate {
+            dot.node(&name(gate), &[("color", "orange")]);
         }
-        for (np, cost) in wrap.edges[&p].iter() {
-            if path.contains(np) {
-                continue;
+        let mut written = HashSet::new();
+        for (a, edges) in self.adj.iter().enumerate() {
+            for (b, cost) in edges.iter() {
+                // edges into the end have no way back, so pairs can't be ordered
+                if directed || written.insert((a.min(*b), a.max(*b), *cost)) {
+                    dot.edge(&name(a), &name(*b), &[("label", &cost.to_string())]);
+                }
             }
-            let mut path = path.clone();
-            path.insert(*np);
-            q.push_back((*np, path, cur_cost + cost));
         }
+        dot
     }
-    best
 }
 
-fn part1(input: &str) -> Result<usize> {
+fn part1(input: &str) -> Result<Route> {
     let wrap = parse(input, true);
-    Ok(run(&wrap))
+    wrap.dense()?.longest().wrap_err("no route")
 }
 
-fn part2(input: &str) -> Result<usize> {
+fn part2(input: &str) -> Result<Route> {
     let wrap = parse(input, false);
-    Ok(run(&wrap))
+    wrap.dense()?.longest().wrap_err("no route")
+}
+
+fn parse_point(s: &str) -> Result<Point> {
+    let (x, y) = s.split_once(',').wrap_err("expected x,y")?;
+    Ok(Point::new(x.parse()?, y.parse()?))
+}
+
+fn print_stats(input: &str, args: &[String]) -> Result<()> {
+    let grid = Grid::parse(input);
+    let (mut start, mut end) = default_ends(&grid);
+    let mut k = 3;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let val = args
+            .next()
+            .wrap_err_with(|| format!("missing value for {}", arg))?;
+        match arg.as_str() {
+            "--stats" => k = val.parse()?,
+            "--from" => start = parse_point(val)?,
+            "--to" => end = parse_point(val)?,
+            _ => return Err(eyre!("unknown argument {}", arg)),
+        }
+    }
+
+    for (name, p1) in [("slopes", true), ("free climbing", false)] {
+        let stats = parse_between(input, p1, start, end)?.dense()?.stats(k);
+        println!("[*] {}: {} paths", name, stats.paths);
+        if let Some(route) = &stats.shortest {
+            println!("[*] shortest: {}", route.length);
+            print_route(route);
+        }
+        for (i, route) in stats.longest.iter().enumerate() {
+            println!("[*] longest #{}: {}", i + 1, route.length);
+            print_route(route);
+        }
+    }
+    Ok(())
+}
+
+/// `--dot <file> [free]`, the slopes graph unless climbing freely
+fn write_dot(input: &str, args: &[String]) -> Result<()> {
+    let (file, free) = match args {
+        [file] => (file, false),
+        [file, mode] if mode == "free" => (file, true),
+        _ => return Err(eyre!("usage: --dot <file> [free]")),
+    };
+    let graph = parse(input, !free).dense()?;
+    std::fs::write(file, graph.to_dot(!free).to_string())?;
+    Ok(())
+}
+
+fn print_route(route: &Route) {
+    let junctions: Vec<String> = route
+        .junctions
+        .iter()
+        .map(|p| format!("({},{})", p.x, p.y))
+        .collect();
+    println!("    {}", junctions.join(" -> "));
 }
 
 fn main() -> Result<()> {
@@ -115,15 +416,31 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    // e.g. `cargo run -- --stats 5 --from 1,0 --to 139,140`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let show_route = args.first().map(|a| a.as_str()) == Some("--route");
+    if args.first().map(|a| a.as_str()) == Some("--dot") {
+        return write_dot(input, &args[1..]);
+    }
+    if !args.is_empty() && !show_route {
+        return print_stats(input, &args);
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
-    println!("[*] part 1: {} ({:?})", res, time);
+    println!("[*] part 1: {} ({:?})", res.length, time);
+    if show_route {
+        print_route(&res);
+    }
 
     let instant = Instant::now();
     let res = part2(input)?;
     let time = Instant::now() - instant;
-    println!("[*] part 2: {} ({:?})", res, time);
+    println!("[*] part 2: {} ({:?})", res.length, time);
+    if show_route {
+        print_route(&res);
+    }
 
     Ok(())
 }
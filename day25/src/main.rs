@@ -1,11 +1,13 @@
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
+use common::dot::Dot;
 use gxhash::{HashMap, HashMapExt, HashSet};
 use rand::seq::SliceRandom;
 use std::time::Instant;
 
 type Graph = Vec<Vec<usize>>;
 
-fn parse(input: &str) -> Graph {
+/// Component names and the wiring between them
+fn parse(input: &str) -> (Vec<String>, Graph) {
     let mut res: HashMap<String, Vec<String>> = Default::default();
     for line in input.lines() {
         let mut s = line.split_whitespace();
@@ -24,6 +26,11 @@ fn parse(input: &str) -> Graph {
         name_map.insert(name.clone(), i);
     }
 
+    let mut names = vec![String::new(); res.len()];
+    for (name, i) in name_map.iter() {
+        names[*i].clone_from(name);
+    }
+
     let mut res2 = vec![vec![]; res.len()];
     for (name, neighs) in res {
         let i = name_map[&name];
@@ -31,7 +38,7 @@ fn parse(input: &str) -> Graph {
             res2[i].push(name_map[&neigh]);
         }
     }
-    res2
+    (names, res2)
 }
 
 fn karger_min_cut(graph: &Graph) -> (usize, Vec<Vec<usize>>) {
@@ -151,7 +158,7 @@ fn minimum_cut_phase(graph: &Graph, nodes: &[usize], start: usize) -> (usize, us
 }
 
 fn part1(input: &str) -> Result<usize> {
-    let graph = parse(input);
+    let (_, graph) = parse(input);
     for i in 0..1000 {
         let (min_cut, partitions) = karger_min_cut(&graph);
         if min_cut == 3 {
@@ -164,18 +171,60 @@ fn part1(input: &str) -> Result<usize> {
 }
 
 fn part2(input: &str) -> Result<usize> {
-    let graph = parse(input);
+    let (_, graph) = parse(input);
     let (min_cut, partition) = stoer_wagner_min_cut(&graph);
     assert_eq!(min_cut, 3);
     let r = partition.len() * (graph.len() - partition.len());
     Ok(r)
 }
 
+/// Components coloured by the side of the minimum cut they end on, cut
+/// wires in bold red
+fn to_dot(names: &[String], graph: &Graph) -> Dot {
+    let (_, partition) = stoer_wagner_min_cut(graph);
+    let mut side = vec![false; graph.len()];
+    for v in partition {
+        side[v] = true;
+    }
+
+    let mut dot = Dot::new("components", false);
+    dot.default_node(&[("style", "filled")]);
+    for (v, name) in names.iter().enumerate() {
+        let color = if side[v] { "lightblue" } else { "orange" };
+        dot.node(name, &[("fillcolor", color)]);
+    }
+    for (u, neighs) in graph.iter().enumerate() {
+        for v in neighs.iter().filter(|v| u < **v) {
+            if side[u] != side[*v] {
+                dot.edge(
+                    &names[u],
+                    &names[*v],
+                    &[("color", "red"), ("penwidth", "3")],
+                );
+            } else {
+                dot.edge(&names[u], &names[*v], &[]);
+            }
+        }
+    }
+    dot
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match &args[..] {
+        [] => {}
+        [cmd, file] if cmd == "--dot" => {
+            let (names, graph) = parse(input);
+            std::fs::write(file, to_dot(&names, &graph).to_string())?;
+            return Ok(());
+        }
+        _ => return Err(eyre!("usage: --dot <file>")),
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
@@ -1,6 +1,11 @@
-use std::{str::FromStr, time::Instant};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+    str::FromStr,
+    time::Instant,
+};
 
-use color_eyre::eyre::{ContextCompat, Error, Result};
+use color_eyre::eyre::{eyre, ContextCompat, Error, Result};
 
 #[derive(Debug)]
 struct Map {
@@ -9,10 +14,161 @@ struct Map {
     pub size: usize,
 }
 
+#[derive(Debug)]
+struct CategoryMap {
+    pub from: String,
+    pub to: String,
+    pub maps: Vec<Map>,
+}
+
+/// `start..end` translated by `offset`
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    pub start: usize,
+    pub end: usize,
+    pub offset: isize,
+}
+
+impl Segment {
+    fn shift(&self, v: usize) -> usize {
+        v.checked_add_signed(self.offset).unwrap()
+    }
+
+    fn image(&self) -> Range<usize> {
+        self.shift(self.start)..self.shift(self.end)
+    }
+}
+
+/// Piecewise-linear function, segments are sorted and never overlap.
+/// Values outside of every segment have no image.
+#[derive(Debug, Clone)]
+struct Piecewise {
+    segments: Vec<Segment>,
+}
+
+impl Piecewise {
+    fn identity() -> Self {
+        let segments = vec![Segment {
+            start: 0,
+            end: usize::MAX,
+            offset: 0,
+        }];
+        Piecewise { segments }
+    }
+
+    fn from_maps(maps: &[Map]) -> Result<Self> {
+        let mut mapped: Vec<Segment> = maps
+            .iter()
+            .map(|m| Segment {
+                start: m.src,
+                end: m.src + m.size,
+                offset: m.dst as isize - m.src as isize,
+            })
+            .collect();
+        mapped.sort_by_key(|s| s.start);
+
+        // unmapped values keep their number
+        let mut segments = vec![];
+        let mut cur = 0;
+        for s in mapped {
+            if s.start < cur {
+                return Err(eyre!("overlapping map at {}", s.start));
+            }
+            if s.start > cur {
+                segments.push(Segment {
+                    start: cur,
+                    end: s.start,
+                    offset: 0,
+                });
+            }
+            segments.push(s);
+            cur = s.end;
+        }
+        if cur < usize::MAX {
+            segments.push(Segment {
+                start: cur,
+                end: usize::MAX,
+                offset: 0,
+            });
+        }
+        Ok(Piecewise { segments })
+    }
+
+    fn apply(&self, v: usize) -> Option<usize> {
+        let i = self.segments.partition_point(|s| s.end <= v);
+        let s = self.segments.get(i)?;
+        (s.start <= v).then(|| s.shift(v))
+    }
+
+    fn apply_range(&self, r: Range<usize>) -> Vec<Range<usize>> {
+        let mut res = vec![];
+        // an empty range would still cut an empty piece out of its segment
+        if r.is_empty() {
+            return res;
+        }
+        let i = self.segments.partition_point(|s| s.end <= r.start);
+        for s in self.segments[i..].iter().take_while(|s| s.start < r.end) {
+            let start = r.start.max(s.start);
+            let end = r.end.min(s.end);
+            res.push(s.shift(start)..s.shift(end));
+        }
+        res
+    }
+
+    /// `self` followed by `other`
+    fn then(&self, other: &Piecewise) -> Piecewise {
+        let mut segments = vec![];
+        for s in self.segments.iter() {
+            let img = s.image();
+            let i = other.segments.partition_point(|o| o.end <= img.start);
+            for o in other.segments[i..].iter().take_while(|o| o.start < img.end) {
+                let start = img.start.max(o.start);
+                let end = img.end.min(o.end);
+                segments.push(Segment {
+                    start: start.checked_add_signed(-s.offset).unwrap(),
+                    end: end.checked_add_signed(-s.offset).unwrap(),
+                    offset: s.offset + o.offset,
+                });
+            }
+        }
+        segments.sort_by_key(|s| s.start);
+
+        let mut merged: Vec<Segment> = vec![];
+        for s in segments {
+            match merged.last_mut() {
+                Some(last) if last.end == s.start && last.offset == s.offset => last.end = s.end,
+                _ => merged.push(s),
+            }
+        }
+        Piecewise { segments: merged }
+    }
+
+    fn invert(&self) -> Result<Piecewise> {
+        let mut segments: Vec<Segment> = self
+            .segments
+            .iter()
+            .map(|s| {
+                let img = s.image();
+                Segment {
+                    start: img.start,
+                    end: img.end,
+                    offset: -s.offset,
+                }
+            })
+            .collect();
+        segments.sort_by_key(|s| s.start);
+        if let Some(w) = segments.windows(2).find(|w| w[0].end > w[1].start) {
+            return Err(eyre!("map is not invertible at {}", w[1].start));
+        }
+        Ok(Piecewise { segments })
+    }
+}
+
 #[derive(Debug)]
 struct Almanac {
     seeds: Vec<usize>,
-    maps: Vec<Vec<Map>>,
+    maps: Vec<CategoryMap>,
+    graph: HashMap<String, Vec<usize>>,
 }
 
 impl FromStr for Almanac {
@@ -26,15 +182,22 @@ impl FromStr for Almanac {
             .split_once(": ")
             .wrap_err("no seeds")?;
         let seeds = seeds
-            .split(" ")
+            .split(' ')
             .filter_map(|v| v.parse::<usize>().ok())
             .collect();
         let mut maps = vec![];
+        let mut graph: HashMap<String, Vec<usize>> = HashMap::new();
         for s in it {
+            let mut lines = s.lines();
+            let header = lines.next().wrap_err("no map header")?;
+            let (from, to) = header
+                .strip_suffix(" map:")
+                .and_then(|h| h.split_once("-to-"))
+                .wrap_err_with(|| format!("invalid map header: {}", header))?;
             let mut m = vec![];
-            for l in s.lines().skip(1) {
+            for l in lines {
                 let vals: Vec<usize> = l
-                    .split(" ")
+                    .split(' ')
                     .filter_map(|v| v.parse::<usize>().ok())
                     .collect();
                 let map = Map {
@@ -44,45 +207,93 @@ impl FromStr for Almanac {
                 };
                 m.push(map);
             }
-            maps.push(m);
+            graph.entry(from.to_string()).or_default().push(maps.len());
+            maps.push(CategoryMap {
+                from: from.to_string(),
+                to: to.to_string(),
+                maps: m,
+            });
         }
-        Ok(Almanac { seeds, maps })
+        Ok(Almanac { seeds, maps, graph })
     }
 }
 
-fn find_location(seed: usize, maps: &Vec<Vec<Map>>) -> usize {
-    let mut location = seed;
-    for map in maps.iter() {
-        for m in map.iter() {
-            let r = m.src..m.src + m.size;
-            if r.contains(&location) {
-                location = m.dst + (location - m.src);
-                break;
+impl Almanac {
+    /// Maps to go through to convert `from` into `to`
+    fn chain(&self, from: &str, to: &str) -> Option<Vec<&CategoryMap>> {
+        let mut prev: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(cat) = queue.pop_front() {
+            if cat == to {
+                let mut chain = vec![];
+                let mut cur = to;
+                while cur != from {
+                    let m = &self.maps[prev[cur]];
+                    chain.push(m);
+                    cur = &m.from;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            for &i in self.graph.get(cat).into_iter().flatten() {
+                let next = self.maps[i].to.as_str();
+                if next != from && !prev.contains_key(next) {
+                    prev.insert(next, i);
+                    queue.push_back(next);
+                }
             }
         }
+        None
+    }
+
+    fn compose(chain: &[&CategoryMap]) -> Result<Piecewise> {
+        let mut f = Piecewise::identity();
+        for m in chain {
+            f = f.then(&Piecewise::from_maps(&m.maps)?);
+        }
+        Ok(f)
+    }
+
+    /// Single function converting `from` into `to`, going through inverted maps if needed
+    fn function(&self, from: &str, to: &str) -> Result<Piecewise> {
+        if let Some(chain) = self.chain(from, to) {
+            Self::compose(&chain)
+        } else if let Some(chain) = self.chain(to, from) {
+            Self::compose(&chain)?.invert()
+        } else {
+            Err(eyre!("no conversion from {} to {}", from, to))
+        }
+    }
+
+    fn map(&self, from: &str, to: &str, value: usize) -> Result<usize> {
+        self.function(from, to)?
+            .apply(value)
+            .wrap_err_with(|| format!("{} {} has no {}", from, value, to))
+    }
+
+    fn map_range(&self, from: &str, to: &str, range: Range<usize>) -> Result<Vec<Range<usize>>> {
+        Ok(self.function(from, to)?.apply_range(range))
     }
-    location
 }
 
 fn part1(input: &str) -> Result<usize> {
     let almanac: Almanac = input.parse()?;
-    let mut locations = vec![];
-    for seed in almanac.seeds.iter() {
-        let location = find_location(*seed, &almanac.maps);
-        locations.push(location);
-    }
-    let min = locations.iter().min().wrap_err("no min")?;
-    Ok(*min)
+    let f = almanac.function("seed", "location")?;
+    let min = almanac
+        .seeds
+        .iter()
+        .filter_map(|seed| f.apply(*seed))
+        .min()
+        .wrap_err("no min")?;
+    Ok(min)
 }
 
 fn part2(input: &str) -> Result<usize> {
     let almanac: Almanac = input.parse()?;
     let mut locations = vec![];
     for chunk in almanac.seeds.chunks(2) {
-        for seed in chunk[0]..chunk[0] + chunk[1] {
-            let location = find_location(seed, &almanac.maps);
-            locations.push(location);
-        }
+        let ranges = almanac.map_range("seed", "location", chunk[0]..chunk[0] + chunk[1])?;
+        locations.extend(ranges.into_iter().map(|r| r.start));
     }
     let min = locations.iter().min().wrap_err("no min")?;
     Ok(*min)
@@ -93,6 +304,15 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    // e.g. `cargo run -- location seed 318728750`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [from, to, value] = args.as_slice() {
+        let almanac: Almanac = input.parse()?;
+        let res = almanac.map(from, to, value.parse()?)?;
+        println!("[*] {} {} -> {} {}", from, value, to, res);
+        return Ok(());
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
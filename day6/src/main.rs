@@ -1,57 +1,91 @@
-use std::time::Instant;
+use std::{ops::RangeInclusive, time::Instant};
 
-use color_eyre::eyre::{Context, ContextCompat, Result};
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 
-fn parse_line1(l: &str) -> Result<Vec<usize>> {
+fn parse_line1(l: &str) -> Result<Vec<u128>> {
     let (_, vals) = l.split_once(": ").wrap_err("invalid times")?;
     let vals = vals
-        .trim()
         .split_whitespace()
-        .filter(|v| !v.is_empty())
-        .map(|v| v.parse::<usize>().unwrap())
-        .collect();
+        .map(|v| v.parse::<u128>())
+        .collect::<Result<_, _>>()?;
     Ok(vals)
 }
 
-fn parse_line2(l: &str) -> Result<usize> {
+fn parse_line2(l: &str) -> Result<u128> {
     let (_, vals) = l.split_once(": ").wrap_err("invalid times")?;
     let val = vals
-        .trim()
         .split_whitespace()
-        .filter(|v| !v.is_empty())
         .collect::<String>()
-        .parse::<usize>()?;
+        .parse::<u128>()?;
     Ok(val)
 }
 
+/// Hold times beating the record, i.e. `t * (time - t) > record`, both ends
+/// included. The bounds come from the roots of `t² - time * t + record`,
+/// then get nudged so ties with the record are left out.
+fn winning_holds(time: u128, record: u128) -> Result<Option<RangeInclusive<u128>>> {
+    let square = time
+        .checked_mul(time)
+        .wrap_err_with(|| format!("race of {} is too long", time))?;
+    let four_record = record
+        .checked_mul(4)
+        .wrap_err_with(|| format!("record {} is too large", record))?;
+    let Some(disc) = square.checked_sub(four_record) else {
+        return Ok(None);
+    };
+
+    let beats = |t: u128| t * (time - t) > record;
+    let mut lo = (time - disc.isqrt()) / 2;
+    while lo > 0 && beats(lo - 1) {
+        lo -= 1;
+    }
+    while lo <= time / 2 && !beats(lo) {
+        lo += 1;
+    }
+    if lo > time / 2 {
+        return Ok(None);
+    }
+    // the distance is symmetric around time / 2
+    Ok(Some(lo..=time - lo))
+}
+
+fn ways(holds: &Option<RangeInclusive<u128>>) -> u128 {
+    holds.as_ref().map_or(0, |h| h.end() - h.start() + 1)
+}
+
 fn part1(input: &str) -> Result<usize> {
     let mut it = input.lines();
-    let times = parse_line1(it.next().unwrap()).wrap_err("invalid times")?;
-    let dists = parse_line1(it.next().unwrap()).wrap_err("invalid distances")?;
+    let times = parse_line1(it.next().wrap_err("no times")?).wrap_err("invalid times")?;
+    let dists = parse_line1(it.next().wrap_err("no distances")?).wrap_err("invalid distances")?;
 
     let mut total = 1;
-    for (max_t, best_d) in times.iter().zip(dists.iter()) {
-        let mut count = 0;
-        for t in 0..*max_t {
-            let d = t * (max_t - t);
-            if d > *best_d { count += 1; }
-        }
-        total *= count;
+    for (time, record) in times.iter().zip(dists.iter()) {
+        total *= ways(&winning_holds(*time, *record)?);
     }
-    Ok(total)
+    Ok(total.try_into()?)
 }
 
 fn part2(input: &str) -> Result<usize> {
     let mut it = input.lines();
-    let max_t = parse_line2(it.next().unwrap()).wrap_err("invalid times")?;
-    let best_d = parse_line2(it.next().unwrap()).wrap_err("invalid distances")?;
+    let time = parse_line2(it.next().wrap_err("no times")?).wrap_err("invalid times")?;
+    let record = parse_line2(it.next().wrap_err("no distances")?).wrap_err("invalid distances")?;
+    Ok(ways(&winning_holds(time, record)?).try_into()?)
+}
 
-    let mut count = 0;
-    for t in 0..max_t {
-        let d = t * (max_t - t);
-        if d > best_d { count += 1; }
+fn print_holds(time: u128, record: u128) -> Result<()> {
+    let holds = winning_holds(time, record)?;
+    match &holds {
+        Some(h) => println!(
+            "[*] race {} / {}: hold {}..={} ({} ways)",
+            time,
+            record,
+            h.start(),
+            h.end(),
+            ways(&holds)
+        ),
+        None => println!("[*] race {} / {}: record can't be beaten", time, record),
     }
-    Ok(count)
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -59,6 +93,27 @@ fn main() -> Result<()> {
 
     let input = include_str!("../input");
 
+    // e.g. `cargo run -- --race 71530 940200`, or `--holds` for the input races
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match &args[..] {
+        [] => {}
+        [cmd] if cmd == "--holds" => {
+            let mut it = input.lines();
+            let (times, dists) = (
+                it.next().wrap_err("no times")?,
+                it.next().wrap_err("no distances")?,
+            );
+            for (time, record) in parse_line1(times)?.into_iter().zip(parse_line1(dists)?) {
+                print_holds(time, record)?;
+            }
+            return print_holds(parse_line2(times)?, parse_line2(dists)?);
+        }
+        [cmd, time, record] if cmd == "--race" => {
+            return print_holds(time.parse()?, record.parse()?);
+        }
+        _ => return Err(eyre!("usage: --holds | --race <time> <record>")),
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, time::Instant};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
+use common::dot::Dot;
 
 enum Direction {
     Left,
@@ -105,11 +106,45 @@ fn part2(input: &str) -> Result<usize> {
     Ok(lcm)
 }
 
+/// Network with start nodes in green, end nodes in red and edges labelled
+/// with the instruction taking them
+fn to_dot(graph: &HashMap<&str, [String; 2]>) -> Dot {
+    let mut dot = Dot::new("network", true);
+    let mut nodes: Vec<&str> = graph.keys().copied().collect();
+    nodes.sort();
+    for node in nodes {
+        if node.ends_with('A') {
+            dot.node(node, &[("style", "filled"), ("fillcolor", "green")]);
+        } else if node.ends_with('Z') {
+            dot.node(node, &[("style", "filled"), ("fillcolor", "red")]);
+        }
+        let [left, right] = &graph[node];
+        if left == right {
+            dot.edge(node, left, &[("label", "LR")]);
+        } else {
+            dot.edge(node, left, &[("label", "L")]);
+            dot.edge(node, right, &[("label", "R")]);
+        }
+    }
+    dot
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match &args[..] {
+        [] => {}
+        [cmd, file] if cmd == "--dot" => {
+            let (_, graph) = parse_input(input);
+            std::fs::write(file, to_dot(&graph).to_string())?;
+            return Ok(());
+        }
+        _ => return Err(eyre!("usage: --dot <file>")),
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;
@@ -1,60 +1,106 @@
-use std::time::Instant;
-
-use color_eyre::eyre::Result;
-
-fn part1(input: &str) -> Result<isize> {
-    let mut sum = 0;
-    for l in input.lines() {
-        let mut vals: Vec<isize> = l
-            .split(" ")
-            .filter_map(|v| v.parse::<isize>().ok())
-            .collect();
-        let mut seqs = vec![vals.clone()];
-        let mut zero = false;
-        while !zero {
-            vals = vals.windows(2).map(|w| w[1] - w[0]).collect();
-            zero = vals.iter().all(|v| *v == 0);
-            seqs.push(vals.clone());
+use std::{str::FromStr, time::Instant};
+
+use color_eyre::eyre::{eyre, Error, Result};
+use num_bigint::BigInt;
+
+/// Polynomial through a sequence, as the first value of each row of its
+/// forward difference table, the last row being zero
+#[derive(Debug, Clone)]
+struct Sequence {
+    len: usize,
+    diffs: Vec<BigInt>,
+}
+
+impl FromStr for Sequence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut vals: Vec<BigInt> = s
+            .split_whitespace()
+            .map(|v| v.parse::<BigInt>())
+            .collect::<Result<_, _>>()?;
+        let len = vals.len();
+        let mut diffs = vec![];
+        // a zero row must be left to tell the polynomial apart from any other
+        while !vals.iter().all(|v| *v == BigInt::ZERO) {
+            if vals.len() <= 1 {
+                return Err(eyre!("{:?} is not polynomial within {} values", s, len));
+            }
+            diffs.push(vals[0].clone());
+            vals = vals.windows(2).map(|w| &w[1] - &w[0]).collect();
         }
-        let mut last = 0;
-        for s in seqs.iter().rev() {
-            let l = s.iter().last().unwrap();
-            last = last + l;
+        if vals.is_empty() {
+            return Err(eyre!("empty sequence"));
         }
-        sum += last;
+        Ok(Sequence { len, diffs })
     }
-    Ok(sum)
 }
 
-fn part2(input: &str) -> Result<isize> {
-    let mut sum = 0;
-    for l in input.lines() {
-        let mut vals: Vec<isize> = l
-            .split(" ")
-            .filter_map(|v| v.parse::<isize>().ok())
-            .collect();
-        let mut seqs = vec![vals.clone()];
-        let mut zero = false;
-        while !zero {
-            vals = vals.windows(2).map(|w| w[1] - w[0]).collect();
-            zero = vals.iter().all(|v| *v == 0);
-            seqs.push(vals.clone());
-        }
-        let mut first = 0;
-        for s in seqs.iter().rev() {
-            let l = s.iter().next().unwrap();
-            first = l - first;
+impl Sequence {
+    fn degree(&self) -> Option<usize> {
+        self.diffs.len().checked_sub(1)
+    }
+
+    /// Value at index `n`, negative indices going before the first value,
+    /// with Newton's forward formula `sum(diffs[k] * binomial(n, k))`
+    fn at(&self, n: &BigInt) -> BigInt {
+        let mut res = BigInt::ZERO;
+        let mut binomial = BigInt::from(1);
+        for (k, d) in self.diffs.iter().enumerate() {
+            res += d * &binomial;
+            // exact, binomial(n, k) * (n - k) is binomial(n, k + 1) * (k + 1)
+            binomial = binomial * (n - k) / (k + 1);
         }
-        sum += first;
+        res
     }
+}
+
+fn parse(input: &str) -> Result<Vec<Sequence>> {
+    input.lines().map(|l| l.parse()).collect()
+}
+
+fn part1(input: &str) -> Result<BigInt> {
+    let sum = parse(input)?
+        .iter()
+        .map(|seq| seq.at(&BigInt::from(seq.len)))
+        .sum();
+    Ok(sum)
+}
+
+fn part2(input: &str) -> Result<BigInt> {
+    let sum = parse(input)?
+        .iter()
+        .map(|seq| seq.at(&BigInt::from(-1)))
+        .sum();
     Ok(sum)
 }
 
+/// Value of every sequence at `index`, e.g. `cargo run -- --at -1000000000000`
+fn print_at(input: &str, index: &str) -> Result<()> {
+    let n: BigInt = index.parse()?;
+    let mut sum = BigInt::ZERO;
+    for seq in parse(input)? {
+        let v = seq.at(&n);
+        let degree = seq.degree().map_or("zero".to_string(), |d| d.to_string());
+        println!("{} (degree {})", v, degree);
+        sum += v;
+    }
+    println!("[*] sum: {}", sum);
+    Ok(())
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let input = include_str!("../input");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match &args[..] {
+        [] => {}
+        [cmd, index] if cmd == "--at" => return print_at(input, index),
+        _ => return Err(eyre!("usage: --at <index>")),
+    }
+
     let instant = Instant::now();
     let res = part1(input)?;
     let time = Instant::now() - instant;